@@ -0,0 +1,73 @@
+#![allow(non_snake_case)]
+#![allow(unused_parens)]
+
+//! compare the rendering of a trace through the parameters HashMap (FormaterParamBuilder + render)
+//! with the direct rendering (render_into), run with "cargo bench --bench formater"
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use time::macros::datetime;
+use Htrace::components::context::Context;
+use Htrace::components::formater::{FormaterCompile, FormaterOptions, FormaterParamBuilder};
+use Htrace::components::level::Level;
+use Htrace::components::trace::OneTrace;
+
+fn bench(name: &str, iterations: u32, mut func: impl FnMut())
+{
+	// warmup
+	for _ in 0..iterations / 10
+	{
+		func();
+	}
+
+	let start = Instant::now();
+	for _ in 0..iterations
+	{
+		func();
+	}
+	let elapsed = start.elapsed();
+	println!("{:<40} {:>10.1} ns/trace", name, elapsed.as_nanos() as f64 / iterations as f64);
+}
+
+fn main()
+{
+	// "cargo test --all-targets" run benches without "--bench", only check they work
+	let iterations = if(std::env::args().any(|arg| arg == "--bench")) {200_000} else {100};
+
+	let mut context = Context::default();
+	context.name_set("request");
+	context.extra_set("request_id", "42");
+	let trace = OneTrace {
+		message: "a simple message, with a value of 42".to_string(),
+		date: datetime!(2024-01-31 13:45:10.5 UTC),
+		level: Level::NORMAL,
+		context: Arc::new(context),
+		filename: "src/some/file.rs".to_string(),
+		fileline: 42,
+		uptime: Duration::from_millis(1500),
+		..Default::default()
+	};
+	let lineReturn = " | ".to_string();
+	let options = FormaterOptions::default();
+
+	for formater in [
+		"{time} {lvl} ({thread:>, }{context:>, }{file}:l{line}) : {msg}",
+		"{time:rfc3339} [{lvl:^6}] {file|basename:<12} {extra[request_id]|default(-)} {uptime} : {msg:.20}",
+	]
+	{
+		println!("{}", formater);
+		let compiled = FormaterCompile(&formater.to_string()).unwrap();
+
+		bench("parameters (FormaterParamBuilder+render)", iterations, || {
+			black_box(compiled.render(FormaterParamBuilder(black_box(&trace), &lineReturn)));
+		});
+
+		let mut buffer = String::new();
+		bench("direct (render_into, reused buffer)", iterations, || {
+			buffer.clear();
+			let _ = compiled.render_into(&mut buffer, black_box(&trace), &options, None);
+			black_box(&buffer);
+		});
+	}
+}
@@ -0,0 +1,120 @@
+use crate::components::level::Level;
+
+/// how symbol names are written in backtraces
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BacktraceDemangle
+{
+	/// demangled name without the hash, like "mycrate::network::connect()" (default)
+	Short,
+	/// demangled name with the hash, like "mycrate::network::connect::h0123456789abcdef"
+	Full,
+	/// only the function name, like "connect()"
+	Function,
+}
+
+impl BacktraceDemangle
+{
+	/// name of a symbol in this style, from its demangled name with and without hash
+	pub fn name(&self, withHash: &str, withoutHash: &str) -> String
+	{
+		return match self {
+			BacktraceDemangle::Short => format!("{}()", withoutHash),
+			BacktraceDemangle::Full => withHash.to_string(),
+			BacktraceDemangle::Function => format!("{}()", withoutHash.rsplit("::").next().unwrap_or_default()),
+		};
+	}
+}
+
+/// how backtraces are captured and which frames are kept (see Context::backtracePolicy_set)
+/// used by HTrace! and its variants, HTraceError!, and the log/tracing bridges
+#[derive(Debug, Clone)]
+pub struct BacktracePolicy
+{
+	/// traces of this level and above get a backtrace (default ERROR, None to disable)
+	pub minLevel: Option<Level>,
+	/// maximum number of frames kept (default None : all frames)
+	pub maxFrames: Option<usize>,
+	/// if not empty, only frames of a function starting with one of these prefixes are kept (ex: "mycrate::")
+	pub includePrefixes: Vec<String>,
+	/// frames of a function starting with one of these prefixes are removed (ex: "tokio::")
+	pub excludePrefixes: Vec<String>,
+	/// how function names are written
+	pub demangle: BacktraceDemangle,
+	/// remove frames of std, core and alloc (and rustc sources), default true
+	pub hideStd: bool,
+	/// the first frames of a function starting with one of these prefixes (and std ones between them) are removed,
+	/// the backtrace starts at the first other frame (default: Htrace, the backtrace crate and the log/tracing bridges)
+	pub startMarkers: Vec<String>,
+	/// frames after the first one of a function starting with one of these prefixes are removed
+	/// (default: the runtime and thread starts, like "std::rt::lang_start", ex: "mycrate::main" to stop at main)
+	pub stopMarkers: Vec<String>,
+	/// remove frames already kept (same function, file and line), like the ones of recursive calls, default true
+	pub dedupe: bool,
+}
+
+impl Default for BacktracePolicy
+{
+	fn default() -> Self {
+		return BacktracePolicy {
+			minLevel: Some(Level::ERROR),
+			maxFrames: None,
+			includePrefixes: vec![],
+			excludePrefixes: vec![],
+			demangle: BacktraceDemangle::Short,
+			hideStd: true,
+			startMarkers: ["Htrace::", "backtrace::", "log::", "tracing::", "tracing_core::", "tracing_subscriber::"].map(String::from).to_vec(),
+			stopMarkers: ["std::rt::lang_start", "std::sys::backtrace::__rust_begin_short_backtrace", "std::sys_common::backtrace::__rust_begin_short_backtrace"].map(String::from).to_vec(),
+			dedupe: true,
+		};
+	}
+}
+
+impl BacktracePolicy
+{
+	/// a backtrace is captured for this level
+	pub fn isCaptured(&self, level: Level) -> bool
+	{
+		return self.minLevel.is_some_and(|min| level.tou8() >= min.tou8());
+	}
+
+	/// a frame is kept, from its demangled name (without hash) and its file
+	pub fn isKept(&self, name: &str, filename: Option<&str>) -> bool
+	{
+		let path = Self::path(name);
+
+		if(self.hideStd && (Self::isStd(name) || filename.is_some_and(|file| file.starts_with("/rustc/"))))
+		{
+			return false;
+		}
+		if(!self.includePrefixes.is_empty() && !self.includePrefixes.iter().any(|prefix| path.starts_with(prefix.as_str())))
+		{
+			return false;
+		}
+		return !self.excludePrefixes.iter().any(|prefix| path.starts_with(prefix.as_str()));
+	}
+
+	/// a frame is one of the first ones to remove (see startMarkers), from its demangled name (without hash)
+	pub fn isStart(&self, name: &str) -> bool
+	{
+		return Self::isStd(name) || self.startMarkers.iter().any(|prefix| Self::path(name).starts_with(prefix.as_str()));
+	}
+
+	/// the frames after this one are removed (see stopMarkers), from its demangled name (without hash)
+	pub fn isStop(&self, name: &str) -> bool
+	{
+		return self.stopMarkers.iter().any(|prefix| Self::path(name).starts_with(prefix.as_str()));
+	}
+
+	///////////// PRIVATE ///////////
+
+	/// trait implementations are named like "<mycrate::Type as core::fmt::Display>::fmt"
+	fn path(name: &str) -> &str
+	{
+		return name.strip_prefix('<').unwrap_or(name);
+	}
+
+	fn isStd(name: &str) -> bool
+	{
+		return ["std::", "core::", "alloc::"].iter().any(|prefix| Self::path(name).starts_with(prefix));
+	}
+}
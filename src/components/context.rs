@@ -1,7 +1,14 @@
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use time::UtcOffset;
+use crate::components::backtrace_policy::BacktracePolicy;
 use crate::components::level::Level;
+use crate::components::rate_limit::RateLimit;
+use crate::components::sampling::Sampling;
+use crate::components::span::SpanInfo;
 use crate::modules::module_abstract::ModuleAbstract;
 use crate::thread_manager::ThreadManager;
 
@@ -11,18 +18,46 @@ use crate::thread_manager::ThreadManager;
 ///
 /// A root context in a new thread use the global context as parent.
 /// The context withing the main thread as parent is the global context.
+///
+/// Each context has a unique id, kept by its copies (see id_get).
 #[derive(Clone)]
 pub struct Context
 {
+	_id: u64,
 	_threadName: Option<String>,
 	_name: Option<String>,
 	_modules: HashMap<String, Option<Arc<dyn ModuleAbstract>>>,
 	_minlvl: Option<Level>,
-	_extras: HashMap<String,String>
+	_extras: HashMap<String,String>,
+	_timeOffset: Option<UtcOffset>,
+	_backtracePolicy: Option<BacktracePolicy>,
+	_rateLimit: Option<RateLimit>,
+	_sampling: Option<Sampling>,
+	_spanTraces: Option<Level>,
+	/// id and start of the span of this context (see Span)
+	_spanId: Option<u64>,
+	_spanStart: Option<Instant>,
+	/// spans of a resolved context, from the root one
+	_spanPath: Vec<SpanInfo>,
 }
 
+static CONTEXTID: AtomicU64 = AtomicU64::new(0);
+
 impl Context
 {
+	/// unique id of this context, shared by its copies
+	/// the id of a resolved context (OneTrace.context) is the one of its closest context (the span one, or the global context)
+	pub fn id_get(&self) -> u64
+	{
+		return self._id;
+	}
+
+	/// set the id (for resolved contexts)
+	pub(crate) fn id_set(&mut self, id: u64)
+	{
+		self._id = id;
+	}
+
 	/// add a module to this context (and its childs default)
 	/// Overwrite if the name exist in this context.
 	/// Also overwrite all parent context with same name.
@@ -64,6 +99,109 @@ impl Context
 		self._minlvl.as_ref()
 	}
 
+	/// set the offset used for trace dates (formater "time" and file names), overwriting parent one
+	/// use NONE if you want to use the parent offset (default, UTC for the global context)
+	pub fn timeOffset_set(&mut self, offset: Option<UtcOffset>)
+	{
+		self._timeOffset = offset;
+	}
+
+	/// set the offset used for trace dates to the local offset of the system (UTC if it cannot be determined)
+	/// should be called before starting threads : on some unix systems, the local offset can only be read by a single threaded program
+	pub fn timeOffset_setLocal(&mut self)
+	{
+		self._timeOffset = Some(UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC));
+	}
+
+	/// get the offset used for trace dates
+	pub fn timeOffset_get(&self) -> Option<&UtcOffset>
+	{
+		return self._timeOffset.as_ref();
+	}
+
+	/// set how backtraces are captured for this context, overwriting parent one
+	/// use NONE if you want to use the parent policy (default, BacktracePolicy::default() for the global context)
+	pub fn backtracePolicy_set(&mut self, policy: Option<BacktracePolicy>)
+	{
+		self._backtracePolicy = policy;
+	}
+
+	/// get how backtraces are captured for this context
+	pub fn backtracePolicy_get(&self) -> Option<&BacktracePolicy>
+	{
+		return self._backtracePolicy.as_ref();
+	}
+
+	/// set how many traces of a same call site are emitted for this context, overwriting parent one
+	/// use NONE if you want to use the parent rate limit (default, no rate limit for the global context)
+	pub fn rateLimit_set(&mut self, limit: Option<RateLimit>)
+	{
+		self._rateLimit = limit;
+	}
+
+	/// get how many traces of a same call site are emitted for this context
+	pub fn rateLimit_get(&self) -> Option<&RateLimit>
+	{
+		return self._rateLimit.as_ref();
+	}
+
+	/// set which traces are kept for high volume levels for this context, overwriting parent one
+	/// use NONE if you want to use the parent sampling (default, all traces are kept for the global context)
+	pub fn sampling_set(&mut self, sampling: Option<Sampling>)
+	{
+		self._sampling = sampling;
+	}
+
+	/// get which traces are kept for high volume levels for this context
+	pub fn sampling_get(&self) -> Option<&Sampling>
+	{
+		return self._sampling.as_ref();
+	}
+
+	/// set the level of the enter/exit traces of the spans of this context (and its childs), overwriting parent one
+	/// use NONE if you want to use the parent level (default, no enter/exit traces for the global context)
+	pub fn spanTraces_set(&mut self, level: Option<Level>)
+	{
+		self._spanTraces = level;
+	}
+
+	/// get the level of the enter/exit traces of the spans
+	pub fn spanTraces_get(&self) -> Option<&Level>
+	{
+		return self._spanTraces.as_ref();
+	}
+
+	/// set the id and start of the span of this context
+	pub(crate) fn span_set(&mut self, id: u64, start: Instant)
+	{
+		self._spanId = Some(id);
+		self._spanStart = Some(start);
+	}
+
+	/// get the id of the span of this context (the closest span for a resolved context)
+	pub fn spanId_get(&self) -> Option<u64>
+	{
+		return self._spanId;
+	}
+
+	/// get the start of the span of this context (the closest span for a resolved context)
+	pub fn spanStart_get(&self) -> Option<&Instant>
+	{
+		return self._spanStart.as_ref();
+	}
+
+	/// get the spans of a resolved context (OneTrace.context), from the root span to the closest one (empty outside of spans)
+	pub fn spanPath_get(&self) -> &[SpanInfo]
+	{
+		return &self._spanPath;
+	}
+
+	/// add a span at the end of the span path
+	pub(crate) fn spanPath_push(&mut self, span: SpanInfo)
+	{
+		self._spanPath.push(span);
+	}
+
 	/// set thread name
 	pub(crate) fn threadName_set(&mut self, threadName: impl Into<String>)
 	{
@@ -118,11 +256,20 @@ impl Default for Context
 {
 	fn default() -> Self {
 		return Self {
+			_id: CONTEXTID.fetch_add(1, Ordering::Relaxed),
 			_threadName: ThreadManager::local_getName(),
 			_name: None,
 			_modules: Default::default(),
 			_minlvl: None,
 			_extras: Default::default(),
+			_timeOffset: None,
+			_backtracePolicy: None,
+			_rateLimit: None,
+			_sampling: None,
+			_spanTraces: None,
+			_spanId: None,
+			_spanStart: None,
+			_spanPath: vec![],
 		};
 	}
 }
@@ -131,26 +278,22 @@ impl Debug for Context
 {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("Context")
+			.field("id", &self._id)
 			.field("name", &self._name)
 			.field("threadName", &self._threadName)
 			.field("minlvl", &self._minlvl)
+			.field("timeOffset", &self._timeOffset)
+			.field("backtracePolicy", &self._backtracePolicy)
+			.field("rateLimit", &self._rateLimit)
+			.field("sampling", &self._sampling)
+			.field("spanTraces", &self._spanTraces)
+			.field("spanPath", &self._spanPath)
 			.field("modules", &self._modules.keys().collect::<Vec<_>>())
 			.field("extra", &self._extras.keys().collect::<Vec<_>>())
 			.finish()
 	}
 }
 
-impl Drop for Context
-{
-	fn drop(&mut self) {
-		self._modules.iter().for_each(|(_, module)| {
-			if let Some(module) = module {
-				module.event_onContextExit(&self);
-			}
-		})
-	}
-}
-
 impl Into<Context> for &str
 {
 	fn into(self) -> Context {
@@ -0,0 +1,164 @@
+use std::error::Error;
+use crate::components::hbacktrace::Hbacktrace;
+use crate::components::level::Level;
+use crate::components::trace::TraceSource;
+use crate::htracer::HTracer;
+
+/// marker of TraceableError for std errors
+pub struct StdErrorKind;
+/// marker of TraceableError for boxed std errors (Box<dyn Error> is not a std error)
+pub struct BoxErrorKind;
+/// marker of TraceableError for anyhow::Error (which is not a std error)
+pub struct AnyhowErrorKind;
+/// marker of TraceableError for errors stored as a String or &str
+pub struct StringErrorKind;
+
+/// errors that can be traced with their causes (see HTraceError! and ResultExt)
+/// the type parameter only distinguishes the implementations, it's always inferred
+pub trait TraceableError<M>
+{
+	/// message of the error itself
+	fn error_message(&self) -> String;
+
+	/// messages of the causes of the error (Error::source() chain), from the closest to the root one
+	fn error_causes(&self) -> Vec<String>
+	{
+		return vec![];
+	}
+
+	/// backtrace captured by the error itself (anyhow::Error, with RUST_BACKTRACE or RUST_LIB_BACKTRACE)
+	fn error_backtrace(&self) -> Option<Vec<Hbacktrace>>
+	{
+		return None;
+	}
+
+	/// the error and its causes : "error : cause : root cause"
+	fn error_chain(&self) -> String
+	{
+		let mut chain = self.error_message();
+		self.error_causes().iter().for_each(|cause| {
+			chain.push_str(" : ");
+			chain.push_str(cause);
+		});
+		return chain;
+	}
+}
+
+impl<E: Error> TraceableError<StdErrorKind> for E
+{
+	fn error_message(&self) -> String {
+		return self.to_string();
+	}
+
+	fn error_causes(&self) -> Vec<String> {
+		return sources(self.source());
+	}
+}
+
+impl TraceableError<BoxErrorKind> for Box<dyn Error>
+{
+	fn error_message(&self) -> String {
+		return self.to_string();
+	}
+
+	fn error_causes(&self) -> Vec<String> {
+		return sources(self.source());
+	}
+}
+
+impl TraceableError<BoxErrorKind> for Box<dyn Error + Send + Sync>
+{
+	fn error_message(&self) -> String {
+		return self.to_string();
+	}
+
+	fn error_causes(&self) -> Vec<String> {
+		return sources(self.source());
+	}
+}
+
+impl TraceableError<AnyhowErrorKind> for anyhow::Error
+{
+	fn error_message(&self) -> String {
+		return self.to_string();
+	}
+
+	fn error_causes(&self) -> Vec<String> {
+		return self.chain().skip(1).map(|cause| cause.to_string()).collect();
+	}
+
+	fn error_backtrace(&self) -> Option<Vec<Hbacktrace>> {
+		let frames = Hbacktrace::fromStd(self.backtrace());
+		return if(frames.is_empty()) {None} else {Some(frames)};
+	}
+}
+
+impl TraceableError<StringErrorKind> for String
+{
+	fn error_message(&self) -> String {
+		return self.clone();
+	}
+}
+
+impl TraceableError<StringErrorKind> for &str
+{
+	fn error_message(&self) -> String {
+		return self.to_string();
+	}
+}
+
+/// trace errors of a Result, and give it back for chaining
+/// ```
+/// use Htrace::components::error::ResultExt;
+/// use Htrace::components::level::Level;
+///
+/// let content = std::fs::read_to_string("idontexist.muahahah")
+///     .htrace_err(Level::WARNING)
+///     .unwrap_or_default();
+/// let content = std::fs::read_to_string("idontexist.muahahah").htrace_ok_or(Level::ERROR, String::new());
+/// ```
+pub trait ResultExt<T, E, M>
+{
+	/// trace the error (with its causes) if any, and return the result unchanged
+	fn htrace_err(self, level: Level) -> Result<T, E>;
+
+	/// trace the error (with its causes) if any, and return the ok value or the default one
+	fn htrace_ok_or(self, level: Level, default: T) -> T;
+}
+
+impl<T, E: TraceableError<M>, M> ResultExt<T, E, M> for Result<T, E>
+{
+	#[track_caller]
+	fn htrace_err(self, level: Level) -> Result<T, E>
+	{
+		if let Err(error) = &self && HTracer::enabled(level)
+		{
+			let caller = std::panic::Location::caller();
+			HTracer::trace_error(&error.error_chain(), error, level, TraceSource {
+				file: caller.file(),
+				line: caller.line(),
+				column: caller.column(),
+				..Default::default()
+			});
+		}
+		return self;
+	}
+
+	#[track_caller]
+	fn htrace_ok_or(self, level: Level, default: T) -> T
+	{
+		return self.htrace_err(level).unwrap_or(default);
+	}
+}
+
+/// messages of an error source and its own sources
+fn sources(mut source: Option<&(dyn Error + 'static)>) -> Vec<String>
+{
+	let mut causes = vec![];
+	while let Some(cause) = source
+	{
+		causes.push(cause.to_string());
+		source = cause.source();
+	}
+	return causes;
+}
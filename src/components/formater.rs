@@ -1,16 +1,119 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::{fmt, io};
+use std::time::Duration;
+use owo_colors::{OwoColorize, Style};
+use time::format_description::OwnedFormatItem;
+use time::format_description::well_known::Rfc3339;
 use time::macros::format_description;
+use time::{OffsetDateTime, UtcOffset};
+use crate::components::span::SpanInfo;
 use crate::components::trace::OneTrace;
-use regex::Regex;
+use crate::Errors;
 
-pub const FORMATTER_VARIABLE: [&str; 8] = ["time","lvl","file","line","msg","thread","context","extra"];
+pub const FORMATTER_VARIABLE: [&str; 18] = ["time","lvl","file","line","column","module","func","msg","backtrace","thread","context","extra","sequence","uptime","delta","span_duration","span_id","span_path"];
 
-pub type FormaterCompilerSignature = fn(formater: &String) -> FormaterCompiled;
+pub type FormaterCompilerSignature = fn(formater: &String) -> Result<FormaterCompiled, Errors>;
 pub type FormaterParamBuilderSignature = fn(&OneTrace, &String) -> HashMap<String, String>;
 
+/// convert a trace into a string, used by modules (CommandLine, File, Network)
+/// the default implementation is TemplateFormatter, any `Fn(&OneTrace) -> String` closure can also be used
+pub trait Formatter: Send + Sync
+{
+	/// render a trace
+	/// highlight is the style of the trace level, when the module support colors (CommandLine)
+	fn format(&self, trace: &OneTrace, highlight: Option<&Style>) -> String;
+
+	/// render a trace at the end of output (allow reusing a buffer)
+	fn format_into(&self, trace: &OneTrace, highlight: Option<&Style>, output: &mut String)
+	{
+		output.push_str(&self.format(trace, highlight));
+	}
+}
+
+impl<F> Formatter for F
+	where F: Fn(&OneTrace) -> String + Send + Sync
+{
+	fn format(&self, trace: &OneTrace, _: Option<&Style>) -> String
+	{
+		return self(trace);
+	}
+}
+
+/// default formatter, render a trace with a template (see FormaterCompile for the syntax)
+/// the highlight style is applied to "lvl" and "msg" (after width/alignment)
+pub struct TemplateFormatter
+{
+	_compiled: FormaterCompiled,
+	_options: FormaterOptions,
+	/// None when the trace is directly rendered (see FormaterCompiled::render_into)
+	_paramBuilder: Option<FormaterParamBuilderSignature>,
+}
+
+impl TemplateFormatter
+{
+	/// compile the template, lineReturn is added after each line return of the message
+	pub fn new(lineFormat: &str, lineReturn: &str) -> Result<Self, Errors>
+	{
+		return Self::with_options(lineFormat, FormaterOptions {
+			lineReturn: lineReturn.to_string(),
+			..Default::default()
+		});
+	}
+
+	/// compile the template, with options for multi-line messages
+	pub fn with_options(lineFormat: &str, options: FormaterOptions) -> Result<Self, Errors>
+	{
+		return Ok(TemplateFormatter {
+			_compiled: FormaterCompile(&lineFormat.to_string())?,
+			_options: options,
+			_paramBuilder: None,
+		});
+	}
+
+	/// same as new(), with a custom compiler and parameters builder
+	/// note: traces are rendered through the parameters HashMap, which is slower than new() and ignore multiline options
+	pub fn with(lineFormat: &str, lineReturn: &str, compiler: FormaterCompilerSignature, paramBuilder: FormaterParamBuilderSignature) -> Result<Self, Errors>
+	{
+		return Ok(TemplateFormatter {
+			_compiled: compiler(&lineFormat.to_string())?,
+			_options: FormaterOptions {
+				lineReturn: lineReturn.to_string(),
+				..Default::default()
+			},
+			_paramBuilder: Some(paramBuilder),
+		});
+	}
+}
+
+impl Formatter for TemplateFormatter
+{
+	fn format(&self, trace: &OneTrace, highlight: Option<&Style>) -> String
+	{
+		let mut output = String::new();
+		self.format_into(trace, highlight, &mut output);
+		return output;
+	}
+
+	fn format_into(&self, trace: &OneTrace, highlight: Option<&Style>, output: &mut String)
+	{
+		match self._paramBuilder
+		{
+			None => {
+				// writing into a String cannot fail
+				let _ = self._compiled.render_into(output, trace, &self._options, highlight);
+			}
+			Some(paramBuilder) => {
+				let parameters = paramBuilder(trace, &self._options.lineReturn);
+				output.push_str(&self._compiled.render_highlight(parameters, highlight));
+			}
+		}
+	}
+}
+
 pub struct FormaterCompiled
 {
-	pub inner: Vec<(String, Option<FormaterData>)>,
+	pub inner: Vec<FormaterPart>,
 }
 
 /// used to store and render the pre-compiled formater
@@ -20,97 +123,968 @@ impl FormaterCompiled
 	/// parameters is a simple array created with FormaterParamBuilder
 	pub fn render(&self, parameters: HashMap<String, String>) -> String
 	{
-		return self.inner.iter().map(|(previous,data)|{
-			if let Some(data) = data
+		return self.render_highlight(parameters, None);
+	}
+
+	/// same as render(), with "lvl" and "msg" colored with the highlight style
+	pub fn render_highlight(&self, parameters: HashMap<String, String>, highlight: Option<&Style>) -> String
+	{
+		let mut output = String::new();
+		Self::renderParts(&self.inner, &parameters, highlight, &mut output);
+		return output;
+	}
+
+	/// render a trace directly into output, without building the parameters (faster than render())
+	/// multi-line messages are rendered following options (see FormaterOptions)
+	pub fn render_into<W: fmt::Write>(&self, output: &mut W, trace: &OneTrace, options: &FormaterOptions, highlight: Option<&Style>) -> fmt::Result
+	{
+		let state = RenderState {
+			trace,
+			options,
+			line: None,
+			// without "{backtrace}", backtraces are appended to the message (like FormaterParamBuilder)
+			backtraceInMsg: !Self::uses(&self.inner, &FormaterVariable::Backtrace),
+		};
+
+		if(options.multiline != FormaterMultiline::Split)
+		{
+			return Self::renderTraceParts(&self.inner, output, &state, highlight);
+		}
+
+		// one rendered line per message line, then one per backtrace
+		let mut lines = MessageLines::new(&trace.message, options.interpretEscapes).map(Cow::Borrowed).collect::<Vec<_>>();
+		if(!trace.backtraces.is_empty())
+		{
+			if let Some(last) = lines.last_mut() && state.backtraceInMsg
+			{
+				*last = Cow::Owned(format!("{}, with : ", last));
+			}
+			lines.extend(trace.backtraces.iter().map(|one| Cow::Owned(one.to_string())));
+		}
+
+		for (index, line) in lines.iter().enumerate()
+		{
+			if(index > 0)
+			{
+				output.write_char('\n')?;
+			}
+			Self::renderTraceParts(&self.inner, output, &RenderState { line: Some(line), ..state }, highlight)?;
+		}
+		return Ok(());
+	}
+
+	/// same as render_into(), for a io::Write (file, socket, etc)
+	pub fn write_into<W: io::Write>(&self, output: &mut W, trace: &OneTrace, options: &FormaterOptions, highlight: Option<&Style>) -> io::Result<()>
+	{
+		let mut adapter = IoAdapter { inner: output, error: None };
+		return match self.render_into(&mut adapter, trace, options, highlight)
+		{
+			Ok(()) => Ok(()),
+			Err(_) => Err(adapter.error.unwrap_or_else(|| io::Error::other("formatter error"))),
+		};
+	}
+
+	/// true if a variable is used in parts
+	fn uses(parts: &[FormaterPart], variable: &FormaterVariable) -> bool
+	{
+		return parts.iter().any(|part| match part
+		{
+			FormaterPart::Text(_) => false,
+			FormaterPart::Variable(data) => data.variable == *variable,
+			FormaterPart::Section(section) => section.variable == *variable || Self::uses(&section.inner, variable),
+		});
+	}
+
+	fn renderTraceParts<W: fmt::Write>(parts: &[FormaterPart], output: &mut W, state: &RenderState, highlight: Option<&Style>) -> fmt::Result
+	{
+		for part in parts
+		{
+			match part
+			{
+				FormaterPart::Text(text) => output.write_str(text)?,
+				FormaterPart::Variable(data) => {
+					let highlight = highlight.filter(|_| data.variable.isHighlighted());
+					data.render_into(data.variable.value(state, data.time.as_ref()), highlight, output)?;
+				}
+				FormaterPart::Section(section) => {
+					if(section.variable.value(state, None).is_empty() == section.ifEmpty)
+					{
+						Self::renderTraceParts(&section.inner, output, state, highlight)?;
+					}
+				}
+			}
+		}
+		return Ok(());
+	}
+
+	fn renderParts(parts: &[FormaterPart], parameters: &HashMap<String, String>, highlight: Option<&Style>, output: &mut String)
+	{
+		parts.iter().for_each(|part| {
+			match part
 			{
-				match parameters.get(&data.data) {
-					None => {}
-					Some(content) => {
-						if(!content.is_empty()) {
-							return format!("{}{}{}{}", previous, data.prefix, parameters.get(&data.data).unwrap_or(&String::new()), data.suffix);
-						}
+				FormaterPart::Text(text) => output.push_str(text),
+				FormaterPart::Variable(data) => {
+					let highlight = highlight.filter(|_| data.variable.isHighlighted());
+					match &data.time {
+						Some(time) => data.render(time.format(parameters.get("time:raw")).as_deref(), highlight, output),
+						None => data.render(parameters.get(&data.data).map(|x| x.as_str()), highlight, output),
+					}
+				}
+				FormaterPart::Section(section) => {
+					let isEmpty = parameters.get(&section.data).map(|x| x.is_empty()).unwrap_or(true);
+					if(isEmpty == section.ifEmpty)
+					{
+						Self::renderParts(&section.inner, parameters, highlight, output);
+					}
+				}
+			}
+		});
+	}
+}
+
+/// how line returns inside a message are rendered
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FormaterMultiline
+{
+	/// each line return is followed by lineReturn, indenting continuation lines (default)
+	Indent,
+	/// each line return is written as "\\n", the trace stays on one line
+	Escape,
+	/// the whole template is rendered for each message line (then for each backtrace line), "{backtrace}" is always empty
+	Split,
+}
+
+impl FormaterMultiline
+{
+	/// name used in configs ("indent", "escape" or "split")
+	pub fn name(&self) -> &'static str
+	{
+		return match self
+		{
+			FormaterMultiline::Indent => "indent",
+			FormaterMultiline::Escape => "escape",
+			FormaterMultiline::Split => "split",
+		};
+	}
+
+	/// mode from its name (see name())
+	pub fn fromName(name: &str) -> Option<Self>
+	{
+		return match name
+		{
+			"indent" => Some(FormaterMultiline::Indent),
+			"escape" => Some(FormaterMultiline::Escape),
+			"split" => Some(FormaterMultiline::Split),
+			_ => None,
+		};
+	}
+}
+
+/// options of the direct rendering (see FormaterCompiled::render_into)
+#[derive(Debug, Clone)]
+pub struct FormaterOptions
+{
+	/// added after each line return of the message (Indent mode)
+	pub lineReturn: String,
+	/// rendering of multi-line messages
+	pub multiline: FormaterMultiline,
+	/// also consider escaped "\\n" and "\\r" (backslash followed by n or r) as line returns
+	pub interpretEscapes: bool,
+}
+
+impl Default for FormaterOptions
+{
+	fn default() -> Self {
+		return FormaterOptions {
+			lineReturn: " | ".to_string(),
+			multiline: FormaterMultiline::Indent,
+			interpretEscapes: true,
+		};
+	}
+}
+
+/// data used while rendering one trace
+#[derive(Clone, Copy)]
+struct RenderState<'a>
+{
+	trace: &'a OneTrace,
+	options: &'a FormaterOptions,
+	/// the message line rendered (Split mode)
+	line: Option<&'a str>,
+	/// backtraces are appended to the message (the formater does not use "{backtrace}")
+	backtraceInMsg: bool,
+}
+
+/// fmt::Write over a io::Write, keeping the io error
+struct IoAdapter<'a, W: io::Write>
+{
+	inner: &'a mut W,
+	error: Option<io::Error>,
+}
+
+impl<W: io::Write> fmt::Write for IoAdapter<'_, W>
+{
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		return self.inner.write_all(s.as_bytes()).map_err(|err| {
+			self.error = Some(err);
+			fmt::Error
+		});
+	}
+}
+
+/// a variable of FORMATTER_VARIABLE, resolved when compiling the formater
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FormaterVariable
+{
+	Time,
+	Lvl,
+	File,
+	Line,
+	Column,
+	Module,
+	Func,
+	Msg,
+	Thread,
+	Context,
+	/// extra data, with its key
+	Extra(String),
+	Sequence,
+	Uptime,
+	Delta,
+	SpanDuration,
+	SpanId,
+	SpanPath,
+	Backtrace,
+}
+
+impl FormaterVariable
+{
+	/// variable from its parameter key (the key used by FormaterParamBuilder, "extra:key" for extras)
+	pub fn fromKey(key: &str) -> Option<Self>
+	{
+		if let Some(extra) = key.strip_prefix("extra:")
+		{
+			return Some(FormaterVariable::Extra(extra.to_string()));
+		}
+
+		return match key
+		{
+			"time" => Some(FormaterVariable::Time),
+			"lvl" => Some(FormaterVariable::Lvl),
+			"file" => Some(FormaterVariable::File),
+			"line" => Some(FormaterVariable::Line),
+			"column" => Some(FormaterVariable::Column),
+			"module" => Some(FormaterVariable::Module),
+			"func" => Some(FormaterVariable::Func),
+			"msg" => Some(FormaterVariable::Msg),
+			"thread" => Some(FormaterVariable::Thread),
+			"context" => Some(FormaterVariable::Context),
+			"sequence" => Some(FormaterVariable::Sequence),
+			"uptime" => Some(FormaterVariable::Uptime),
+			"delta" => Some(FormaterVariable::Delta),
+			"span_duration" => Some(FormaterVariable::SpanDuration),
+			"span_id" => Some(FormaterVariable::SpanId),
+			"span_path" => Some(FormaterVariable::SpanPath),
+			"backtrace" => Some(FormaterVariable::Backtrace),
+			_ => None,
+		};
+	}
+
+	/// true for variables colored by the highlight style (see Formatter::format)
+	fn isHighlighted(&self) -> bool
+	{
+		return matches!(self, FormaterVariable::Lvl | FormaterVariable::Msg);
+	}
+
+	/// value of this variable for a trace, borrowing the trace when possible
+	fn value<'a>(&self, state: &RenderState<'a>, time: Option<&'a FormaterTime>) -> FormaterValue<'a>
+	{
+		let trace = state.trace;
+		return match self
+		{
+			FormaterVariable::Time => FormaterValue::Time(&trace.date, time),
+			FormaterVariable::Lvl => FormaterValue::Str(trace.level.as4LengthStr()),
+			FormaterVariable::File => FormaterValue::Str(&trace.filename),
+			FormaterVariable::Line => FormaterValue::Number(trace.fileline as u64),
+			FormaterVariable::Column => FormaterValue::Number(trace.fileColumn as u64),
+			FormaterVariable::Module => FormaterValue::Str(trace.modulePath),
+			FormaterVariable::Func => FormaterValue::Str(trace.funcName),
+			FormaterVariable::Msg => match state.line {
+				Some(line) => FormaterValue::Str(line),
+				None => FormaterValue::Message(*state),
+			},
+			FormaterVariable::Backtrace => match state.line {
+				Some(_) => FormaterValue::Str(""),
+				None => FormaterValue::Backtrace(*state),
+			},
+			FormaterVariable::Thread => FormaterValue::Str(trace.context.threadName_get().as_deref().unwrap_or_default()),
+			FormaterVariable::Context => FormaterValue::Str(trace.context.name_get().as_deref().unwrap_or_default()),
+			FormaterVariable::Extra(key) => FormaterValue::Str(trace.context.extra_getAll().get(key).map(|x| x.as_str()).unwrap_or_default()),
+			FormaterVariable::Sequence => FormaterValue::Number(trace.sequence),
+			FormaterVariable::Uptime => FormaterValue::Seconds(Some(trace.uptime)),
+			FormaterVariable::Delta => FormaterValue::Seconds(trace.delta),
+			FormaterVariable::SpanDuration => FormaterValue::Seconds(trace.spanDuration),
+			FormaterVariable::SpanId => match trace.context.spanId_get() {
+				Some(id) => FormaterValue::Number(id),
+				None => FormaterValue::Str(""),
+			},
+			FormaterVariable::SpanPath => FormaterValue::SpanPath(trace.context.spanPath_get()),
+		};
+	}
+}
+
+/// value of a variable for one trace, written without intermediate String when possible
+enum FormaterValue<'a>
+{
+	Str(&'a str),
+	Number(u64),
+	Seconds(Option<Duration>),
+	Time(&'a OffsetDateTime, Option<&'a FormaterTime>),
+	/// message with line returns, and backtraces if backtraceInMsg (see FormaterParamBuilder)
+	Message(RenderState<'a>),
+	/// backtraces, each on a new line
+	Backtrace(RenderState<'a>),
+	/// spans from the root one, like "request#1/route#2"
+	SpanPath(&'a [SpanInfo]),
+}
+
+impl FormaterValue<'_>
+{
+	fn is_empty(&self) -> bool
+	{
+		return match self
+		{
+			FormaterValue::Str(content) => content.is_empty(),
+			FormaterValue::Seconds(seconds) => seconds.is_none(),
+			FormaterValue::Message(state) => state.trace.message.is_empty() && (!state.backtraceInMsg || state.trace.backtraces.is_empty()),
+			FormaterValue::Backtrace(state) => state.trace.backtraces.is_empty(),
+			FormaterValue::SpanPath(path) => path.is_empty(),
+			FormaterValue::Time(date, Some(time)) => time.format_date(date).is_none(),
+			FormaterValue::Number(_) | FormaterValue::Time(_, None) => false,
+		};
+	}
+
+	fn write<W: fmt::Write>(&self, output: &mut W) -> fmt::Result
+	{
+		return match self
+		{
+			FormaterValue::Str(content) => output.write_str(content),
+			FormaterValue::Number(number) => write!(output, "{}", number),
+			FormaterValue::Seconds(None) => Ok(()),
+			FormaterValue::Seconds(Some(seconds)) => write!(output, "{:.6}", seconds.as_secs_f64()),
+			FormaterValue::Time(date, None) => write!(output, "{:02}:{:02}:{:02}.{:06}", date.hour(), date.minute(), date.second(), date.microsecond()),
+			FormaterValue::Time(date, Some(time)) => time.write_date(date, output),
+			FormaterValue::Message(state) => {
+				for (index, line) in MessageLines::new(&state.trace.message, state.options.interpretEscapes).enumerate()
+				{
+					if(index > 0)
+					{
+						writeLineReturn(output, state.options)?;
+					}
+					output.write_str(line)?;
+				}
+				if(state.backtraceInMsg && !state.trace.backtraces.is_empty())
+				{
+					output.write_str(", with : ")?;
+					FormaterValue::Backtrace(*state).write(output)?;
+				}
+				Ok(())
+			}
+			FormaterValue::Backtrace(state) => {
+				for one in &state.trace.backtraces
+				{
+					writeLineReturn(output, state.options)?;
+					write!(output, "{}", one)?;
+				}
+				Ok(())
+			}
+			FormaterValue::SpanPath(path) => {
+				for (index, span) in path.iter().enumerate()
+				{
+					if(index > 0)
+					{
+						output.write_char('/')?;
 					}
+					write!(output, "{}", span)?;
 				}
+				Ok(())
 			}
-			return previous.clone();
-		}).collect::<Vec<String>>().join("");
+		};
+	}
+}
+
+/// line return inside a rendered trace (Indent or Escape mode)
+fn writeLineReturn<W: fmt::Write>(output: &mut W, options: &FormaterOptions) -> fmt::Result
+{
+	return match options.multiline
+	{
+		FormaterMultiline::Escape => output.write_str("\\n"),
+		_ => {
+			output.write_char('\n')?;
+			output.write_str(&options.lineReturn)
+		}
+	};
+}
+
+/// iterate over the lines of a message, splitting on "\n", "\r", "\n\r" (and their escaped forms if interpretEscapes)
+struct MessageLines<'a>
+{
+	rest: Option<&'a str>,
+	interpretEscapes: bool,
+}
+
+impl<'a> MessageLines<'a>
+{
+	fn new(message: &'a str, interpretEscapes: bool) -> Self
+	{
+		return MessageLines { rest: Some(message), interpretEscapes };
 	}
 }
 
-/// used to store data name, and also it's prefix/suffix
+impl<'a> Iterator for MessageLines<'a>
+{
+	type Item = &'a str;
+
+	fn next(&mut self) -> Option<Self::Item>
+	{
+		const LINERETURNS: [&str; 3] = ["\n\r", "\r", "\n"];
+		const ESCAPED_LINERETURNS: [&str; 3] = ["\\n\\r", "\\r", "\\n"];
+
+		let rest = self.rest?;
+		let mut searchFrom = 0;
+		while let Some(position) = rest[searchFrom..].find(['\n', '\r', '\\']).map(|x| x + searchFrom)
+		{
+			let candidate = &rest[position..];
+			let found = LINERETURNS.iter()
+				.chain(ESCAPED_LINERETURNS.iter().filter(|_| self.interpretEscapes))
+				.find(|lineReturnChars| candidate.starts_with(**lineReturnChars));
+			if let Some(lineReturnChars) = found
+			{
+				self.rest = Some(&rest[position + lineReturnChars.len()..]);
+				return Some(&rest[..position]);
+			}
+			searchFrom = position + 1;
+		}
+
+		self.rest = None;
+		return Some(rest);
+	}
+}
+
+/// one part of a compiled formater
+#[derive(Debug)]
+pub enum FormaterPart
+{
+	/// raw text
+	Text(String),
+	/// a variable, with its options
+	Variable(FormaterData),
+	/// a conditional section
+	Section(FormaterSection),
+}
+
+/// alignment of a variable inside its width
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FormaterAlign
+{
+	Left,
+	Right,
+	Center,
+}
+
+/// transformation applied to a variable content, in order
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FormaterFilter
+{
+	/// uppercase
+	Upper,
+	/// lowercase
+	Lower,
+	/// keep only the last element of a path (mostly for "file")
+	Basename,
+	/// remove leading and trailing whitespaces
+	Trim,
+	/// replace an empty or missing content
+	Default(String),
+}
+
+impl FormaterFilter
+{
+	pub fn apply(&self, content: String) -> String
+	{
+		return match self
+		{
+			FormaterFilter::Upper => content.to_uppercase(),
+			FormaterFilter::Lower => content.to_lowercase(),
+			FormaterFilter::Basename => content.rsplit(['/', '\\']).next().unwrap_or_default().to_string(),
+			FormaterFilter::Trim => content.trim().to_string(),
+			FormaterFilter::Default(default) => {
+				if(content.is_empty()) {default.clone()} else {content}
+			}
+		};
+	}
+}
+
+/// format of the "time" variable, set with `{time:FORMAT}`
+#[derive(Debug)]
+pub enum FormaterTime
+{
+	/// `{time:rfc3339}` : 2024-01-31T13:45:00.123456789+01:00
+	Rfc3339,
+	/// `{time:unix}` : seconds since unix epoch
+	Unix,
+	/// `{time:unix_ms}` : milliseconds since unix epoch
+	UnixMs,
+	/// `{time:iso_date}` : 2024-01-31
+	IsoDate,
+	/// any "time" crate format description, ex: `{time:[year]/[month]/[day] [hour]:[minute]}`
+	Custom(OwnedFormatItem),
+}
+
+impl FormaterTime
+{
+	/// format the raw time parameter (see FormaterParamBuilder)
+	pub fn format(&self, raw: Option<&String>) -> Option<String>
+	{
+		let (nanos, offset) = raw?.split_once(' ')?;
+		let date = OffsetDateTime::from_unix_timestamp_nanos(nanos.parse().ok()?).ok()?
+			.to_offset(UtcOffset::from_whole_seconds(offset.parse().ok()?).ok()?);
+
+		return self.format_date(&date);
+	}
+
+	/// write a date, without allocation for unix presets
+	fn write_date<W: fmt::Write>(&self, date: &OffsetDateTime, output: &mut W) -> fmt::Result
+	{
+		return match self
+		{
+			FormaterTime::Unix => write!(output, "{}", date.unix_timestamp()),
+			FormaterTime::UnixMs => write!(output, "{}", date.unix_timestamp_nanos() / 1_000_000),
+			_ => output.write_str(&self.format_date(date).unwrap_or_default()),
+		};
+	}
+
+	/// format a date
+	pub fn format_date(&self, date: &OffsetDateTime) -> Option<String>
+	{
+		return match self
+		{
+			FormaterTime::Rfc3339 => date.format(&Rfc3339).ok(),
+			FormaterTime::Unix => Some(date.unix_timestamp().to_string()),
+			FormaterTime::UnixMs => Some((date.unix_timestamp_nanos() / 1_000_000).to_string()),
+			FormaterTime::IsoDate => date.format(format_description!("[year]-[month]-[day]")).ok(),
+			FormaterTime::Custom(format) => date.format(format).ok(),
+		};
+	}
+}
+
+/// used to store data name, and also its options (prefix/suffix, filters, width, etc)
 #[derive(Debug)]
 pub struct FormaterData {
 	pub prefix: String,
+	/// parameter key of the variable (see FormaterParamBuilder)
 	pub data: String,
+	pub variable: FormaterVariable,
 	pub suffix: String,
+	pub filters: Vec<FormaterFilter>,
+	pub align: FormaterAlign,
+	/// minimal width (in chars), padded with spaces
+	pub width: Option<usize>,
+	/// maximal length (in chars), truncated
+	pub precision: Option<usize>,
+	/// format of the "time" variable (None for the default format)
+	pub time: Option<FormaterTime>,
 }
 
-/// default formater for Htrace
-/// compile the formater into a faster array inside a FormaterCompiled.render()
-/// you can replace it with FormaterCompilerSignature (and put in inside config of module)
-/// example of a formater string : `{time} {lvl} ({thread:>, }{file}:l{line} |{extra[test]}|) : {msg}`
-/// each variable is present in FORMATTER_VARIABLE
-/// extra is special, it can be used to get extra information from thread or context
-pub fn FormaterCompile(formater: &String) -> FormaterCompiled
+impl FormaterData
 {
-	let mut compiled = vec![];
-	let mut lastchar = 0;
-	let regexstr = format!(r"\{{({})(:([><])([^}}]+))?\}}", FORMATTER_VARIABLE.map(|var|{
-		if(var=="extra")
+	fn new(data: String, variable: FormaterVariable) -> Self
+	{
+		return FormaterData {
+			prefix: String::new(),
+			data,
+			variable,
+			suffix: String::new(),
+			filters: vec![],
+			align: FormaterAlign::Left,
+			width: None,
+			precision: None,
+			time: None,
+		};
+	}
+
+	/// render the variable content (nothing at all, not even prefix/suffix, if the content is empty)
+	/// the highlight style only apply to the content (not the padding, prefix or suffix)
+	pub fn render(&self, content: Option<&str>, highlight: Option<&Style>, output: &mut String)
+	{
+		// writing into a String cannot fail
+		let _ = self.renderStr(content.unwrap_or_default(), highlight, output);
+	}
+
+	/// same as render(), from a trace value
+	fn render_into<W: fmt::Write>(&self, value: FormaterValue, highlight: Option<&Style>, output: &mut W) -> fmt::Result
+	{
+		if let FormaterValue::Str(content) = value
 		{
-			return r"extra\[([^\]]+)\]";
+			return self.renderStr(content, highlight, output);
 		}
-		return var;
-	}).join("|"));
-	let regex = Regex::new(&regexstr).unwrap();
-	let _ = regex.replace_all(formater, |caps: &regex::Captures| {
-		let mut data = caps.get(1).unwrap().as_str().to_string();
-		let previous = &formater[lastchar..caps.get(0).unwrap().start()];
-		lastchar = caps.get(0).unwrap().end();
 
-		//when extra
-		if(data.starts_with("extra"))
+		// nothing to transform, the value is directly written
+		if(self.filters.is_empty() && self.precision.is_none() && self.width.is_none() && highlight.is_none())
 		{
-			if let Some(extrakey) = caps.get(2)
+			if(value.is_empty())
 			{
-				data=format!("extra:{}",extrakey.as_str());
+				return Ok(());
 			}
+			output.write_str(&self.prefix)?;
+			value.write(output)?;
+			return output.write_str(&self.suffix);
+		}
+
+		let mut content = String::new();
+		value.write(&mut content)?;
+		return self.renderStr(&content, highlight, output);
+	}
+
+	fn renderStr<W: fmt::Write>(&self, content: &str, highlight: Option<&Style>, output: &mut W) -> fmt::Result
+	{
+		let mut filtered = None;
+		for filter in &self.filters
+		{
+			filtered = Some(filter.apply(filtered.unwrap_or_else(|| content.to_string())));
 		}
+		let mut content = filtered.as_deref().unwrap_or(content);
 
-		// prefix/suffix
-		let mut prefix = "".to_string();
-		let mut suffix = "".to_string();
-		if let Some(indicator) = caps.get(4)
+		if(content.is_empty())
 		{
-			if let Some(affix) = caps.get(5)
-			{
-				if(indicator.as_str()=="<")
+			return Ok(());
+		}
+
+		if let Some(precision) = self.precision && let Some((end, _)) = content.char_indices().nth(precision)
+		{
+			content = &content[..end];
+		}
+
+		output.write_str(&self.prefix)?;
+		let padding = match self.width
+		{
+			None => 0,
+			Some(width) => width.saturating_sub(content.chars().count()),
+		};
+		let (before, after) = match self.align
+		{
+			FormaterAlign::Left => (0, padding),
+			FormaterAlign::Right => (padding, 0),
+			FormaterAlign::Center => (padding / 2, padding - padding / 2),
+		};
+		for _ in 0..before
+		{
+			output.write_char(' ')?;
+		}
+		match highlight
+		{
+			Some(style) => write!(output, "{}", content.style(*style))?,
+			None => output.write_str(content)?,
+		}
+		for _ in 0..after
+		{
+			output.write_char(' ')?;
+		}
+		return output.write_str(&self.suffix);
+	}
+}
+
+/// a conditional section, rendered only if a variable is not empty (or only if empty)
+#[derive(Debug)]
+pub struct FormaterSection
+{
+	/// parameter key of the variable (see FormaterParamBuilder)
+	pub data: String,
+	pub variable: FormaterVariable,
+	pub ifEmpty: bool,
+	pub inner: Vec<FormaterPart>,
+}
+
+/// default formater for Htrace
+/// compile the formater into a faster array inside a FormaterCompiled.render()
+/// you can replace it with FormaterCompilerSignature (see TemplateFormatter::with)
+/// example of a formater string : `{time} {lvl} ({thread:>, }{file}:l{line} |{extra[test]}|) : {msg}`
+/// each variable is present in FORMATTER_VARIABLE
+/// extra is special, it can be used to get extra information from thread or context (ex: `{extra[request_id]}`, see thread::extra_set and Context::extra_set)
+/// sequence is the global order of the trace, uptime the seconds since HTracer::globalContext_set(), delta the seconds since the previous trace of the same thread (empty for the first one)
+/// span_duration is the seconds since the closest span was entered (its duration for its exit trace, empty outside of spans)
+/// span_id is the id of the closest span, span_path the spans from the root one with their ids, like "request#1/route#2" (both empty outside of spans)
+/// module is the module path of the trace, func the enclosing function (relative to module), column the column in file (0 when unknown, ex: traces from log or tracing)
+///
+/// syntax of a variable : `{name|filter|filter:spec}` (filters and spec are optional)
+/// * filters: `upper`, `lower`, `basename`, `trim`, `default(text)` (used when the content is empty or missing)
+/// * spec: `[align][width][.precision]` with align `<` (left, default), `>` (right) or `^` (center), ex: `{file:<30}`, `{msg:.200}`
+/// * spec (legacy): `<prefix` or `>suffix`, only written if the content is not empty, ex: `{thread:>, }`
+/// * spec (time only): a preset (`rfc3339`, `unix`, `unix_ms`, `iso_date`) or a "time" crate format description, ex: `{time:[year]-[month]-[day] [hour]:[minute]}`
+///
+/// dates use the offset of the trace context (see Context::timeOffset_set), UTC by default
+///
+/// conditional sections: `{?context}(in {context}){/}` is rendered only if context is not empty, `{!context}...{/}` only if empty
+/// literal braces are written `{{` and `}}`
+/// an invalid formater (unknown variable, unclosed brace or section, invalid spec) return an error
+pub fn FormaterCompile(formater: &String) -> Result<FormaterCompiled, Errors>
+{
+	let error = |position: usize, message: &str| {
+		Errors::FormaterCompileError(formater.to_string(), position, message.to_string())
+	};
+
+	// sections being parsed, with the parts before them
+	let mut sections: Vec<(FormaterSection, Vec<FormaterPart>, usize)> = vec![];
+	let mut compiled = vec![];
+	let mut text = String::new();
+	let mut chars = formater.char_indices().peekable();
+
+	while let Some((position, c)) = chars.next()
+	{
+		match c
+		{
+			'{' if chars.peek().map(|x| x.1) == Some('{') => {
+				chars.next();
+				text.push('{');
+			}
+			'}' if chars.peek().map(|x| x.1) == Some('}') => {
+				chars.next();
+				text.push('}');
+			}
+			'}' => return Err(error(position, "unexpected '}', use '}}' for a literal brace")),
+			'{' => {
+				let Some(end) = formater[position..].find('}').map(|x| x + position)
+				else
+				{
+					return Err(error(position, "unclosed '{', use '{{' for a literal brace"));
+				};
+				let tag = &formater[position + 1..end];
+				while let Some((next, _)) = chars.peek() && *next <= end
+				{
+					chars.next();
+				}
+
+				if(!text.is_empty())
 				{
-					prefix = affix.as_str().to_string();
+					compiled.push(FormaterPart::Text(std::mem::take(&mut text)));
+				}
+
+				if(tag == "/")
+				{
+					let Some((mut section, parent, _)) = sections.pop()
+					else
+					{
+						return Err(error(position, "'{/}' without opened section"));
+					};
+					section.inner = std::mem::replace(&mut compiled, parent);
+					compiled.push(FormaterPart::Section(section));
+				}
+				else if(tag.starts_with('?') || tag.starts_with('!'))
+				{
+					let (data, variable, rest) = parseVariableName(&tag[1..]).map_err(|message| error(position, &message))?;
+					if(!rest.is_empty())
+					{
+						return Err(error(position, "a section only take a variable name"));
+					}
+					sections.push((FormaterSection {
+						data,
+						variable,
+						ifEmpty: tag.starts_with('!'),
+						inner: vec![],
+					}, std::mem::take(&mut compiled), position));
 				}
 				else
 				{
-					suffix = affix.as_str().to_string();
+					let data = parseVariable(tag).map_err(|message| error(position, &message))?;
+					compiled.push(FormaterPart::Variable(data));
 				}
 			}
+			c => text.push(c),
 		}
+	}
 
-		compiled.push((previous.to_string(), Some(FormaterData{
-			prefix: prefix,
-			data,
-			suffix: suffix,
-		})));
-		"".to_string()
-	}).into_owned();
+	if let Some((_, _, position)) = sections.last()
+	{
+		return Err(error(*position, "unclosed section, missing '{/}'"));
+	}
+
+	if(!text.is_empty())
+	{
+		compiled.push(FormaterPart::Text(text));
+	}
+
+	return Ok(FormaterCompiled{ inner: compiled });
+}
+
+/// parse the variable name at the start of a tag, return its key, the variable and the rest of the tag
+fn parseVariableName(tag: &str) -> Result<(String, FormaterVariable, &str), String>
+{
+	let nameEnd = tag.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(tag.len());
+	let name = &tag[..nameEnd];
+	let mut rest = &tag[nameEnd..];
+
+	if(!FORMATTER_VARIABLE.contains(&name))
+	{
+		return Err(format!("unknown variable '{}'", name));
+	}
+
+	if(name != "extra")
+	{
+		let variable = FormaterVariable::fromKey(name).ok_or_else(|| format!("unknown variable '{}'", name))?;
+		return Ok((name.to_string(), variable, rest));
+	}
+
+	// extra need a key : extra[key]
+	let Some(keyContent) = rest.strip_prefix('[')
+	else
+	{
+		return Err("extra need a key, like 'extra[key]'".to_string());
+	};
+	let Some(keyEnd) = keyContent.find(']')
+	else
+	{
+		return Err("unclosed extra key, missing ']'".to_string());
+	};
+	let key = &keyContent[..keyEnd];
+	if(key.is_empty())
+	{
+		return Err("extra need a key, like 'extra[key]'".to_string());
+	}
+	rest = &keyContent[keyEnd + 1..];
+
+	return Ok((format!("extra:{}", key), FormaterVariable::Extra(key.to_string()), rest));
+}
+
+/// parse a variable tag : name|filter|filter:spec
+fn parseVariable(tag: &str) -> Result<FormaterData, String>
+{
+	let (data, variableKind, mut rest) = parseVariableName(tag)?;
+	let mut variable = FormaterData::new(data, variableKind);
+
+	while let Some(filterContent) = rest.strip_prefix('|')
+	{
+		let filterEnd = filterContent.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(filterContent.len());
+		let filter = match &filterContent[..filterEnd]
+		{
+			"upper" => FormaterFilter::Upper,
+			"lower" => FormaterFilter::Lower,
+			"basename" => FormaterFilter::Basename,
+			"trim" => FormaterFilter::Trim,
+			"default" => {
+				let Some(defaultContent) = filterContent[filterEnd..].strip_prefix('(')
+				else
+				{
+					return Err("default need a value, like 'default(text)'".to_string());
+				};
+				let Some(defaultEnd) = defaultContent.find(')')
+				else
+				{
+					return Err("unclosed default value, missing ')'".to_string());
+				};
+				rest = &defaultContent[defaultEnd + 1..];
+				variable.filters.push(FormaterFilter::Default(defaultContent[..defaultEnd].to_string()));
+				continue;
+			}
+			other => return Err(format!("unknown filter '{}'", other)),
+		};
+		variable.filters.push(filter);
+		rest = &filterContent[filterEnd..];
+	}
+
+	if(rest.is_empty())
+	{
+		return Ok(variable);
+	}
+
+	let Some(spec) = rest.strip_prefix(':')
+	else
+	{
+		return Err(format!("unexpected '{}'", rest));
+	};
+
+	if(parseSpec(spec, &mut variable))
+	{
+		return Ok(variable);
+	}
+
+	// legacy prefix/suffix
+	if let Some(prefix) = spec.strip_prefix('<')
+	{
+		variable.prefix = prefix.to_string();
+	}
+	else if let Some(suffix) = spec.strip_prefix('>')
+	{
+		variable.suffix = suffix.to_string();
+	}
+	else if(variable.data == "time")
+	{
+		variable.time = Some(match spec
+		{
+			"rfc3339" => FormaterTime::Rfc3339,
+			"unix" => FormaterTime::Unix,
+			"unix_ms" => FormaterTime::UnixMs,
+			"iso_date" => FormaterTime::IsoDate,
+			format => FormaterTime::Custom(time::format_description::parse_owned::<1>(format)
+				.map_err(|err| format!("invalid time format '{}' : {}", format, err))?),
+		});
+	}
+	else
+	{
+		return Err(format!("invalid spec '{}'", spec));
+	}
+
+	return Ok(variable);
+}
+
+/// parse a "[align][width][.precision]" spec, return false if the spec is not of this form
+fn parseSpec(spec: &str, variable: &mut FormaterData) -> bool
+{
+	let (align, rest) = match spec.chars().next()
+	{
+		Some('<') => (FormaterAlign::Left, &spec[1..]),
+		Some('>') => (FormaterAlign::Right, &spec[1..]),
+		Some('^') => (FormaterAlign::Center, &spec[1..]),
+		_ => (FormaterAlign::Left, spec),
+	};
+
+	let (width, precision) = match rest.split_once('.')
+	{
+		None => (rest, None),
+		Some((width, precision)) => (width, Some(precision)),
+	};
+
+	let width = match width
+	{
+		"" => None,
+		width => match width.parse::<usize>() {
+			Ok(width) => Some(width),
+			Err(_) => return false,
+		},
+	};
+	let precision = match precision
+	{
+		None => None,
+		Some(precision) => match precision.parse::<usize>() {
+			Ok(precision) => Some(precision),
+			Err(_) => return false,
+		},
+	};
 
-	compiled.push((formater[lastchar..].to_string(), None));
+	if(width.is_none() && precision.is_none())
+	{
+		return false;
+	}
 
-	return FormaterCompiled{ inner: compiled };
+	variable.align = align;
+	variable.width = width;
+	variable.precision = precision;
+	return true;
 }
 
 
 /// format a string in a simple way errorless
-/// you can replace it with FormaterParamBuilderSignature (and put in inside config of module)
+/// escapes are always interpreted, and multi-line messages are indented with lineReturn (FormaterMultiline::Indent)
+/// you can replace it with FormaterParamBuilderSignature (see TemplateFormatter::with)
 pub fn FormaterParamBuilder(trace: &OneTrace, lineReturn: &String) -> HashMap<String, String>
 {
 	let mut msg = trace.message.clone();
@@ -125,29 +1099,44 @@ pub fn FormaterParamBuilder(trace: &OneTrace, lineReturn: &String) -> HashMap<St
 		msg = msg.replace("\n",linereturn.as_str());
 	}
 
+	let mut drawBacktraces= "".to_string();
+	trace.backtraces.iter().for_each(|one|{
+		drawBacktraces = format!("{}\n{}{}",drawBacktraces,lineReturn,one.to_string());
+	});
+
+	// msg keep the backtraces, even if "backtrace" is used
 	if(trace.backtraces.len()>0)
 	{
-		let mut drawBacktraces= "".to_string();
-		trace.backtraces.iter().for_each(|one|{
-			drawBacktraces = format!("{}\n{}{}",drawBacktraces,lineReturn,one.to_string());
-		});
-
 		msg = format!("{}, with : {}",msg,drawBacktraces)
 	}
 
 	let mut parameters = HashMap::new();
 	let formatTime = format_description!("[hour repr:24]:[minute]:[second].[subsecond digits:6]");
 	parameters.insert("time".to_string(), trace.date.format(formatTime).unwrap_or("00:00:00.000000".to_string()));
+	// used by `{time:FORMAT}`
+	parameters.insert("time:raw".to_string(), format!("{} {}", trace.date.unix_timestamp_nanos(), trace.date.offset().whole_seconds()));
 	parameters.insert("lvl".to_string(), trace.level.convert4LengthString());
 	parameters.insert("thread".to_string(), trace.context.threadName_get().clone().unwrap_or("".to_string()));
 	parameters.insert("context".to_string(), trace.context.name_get().clone().unwrap_or("".to_string()));
 	parameters.insert("file".to_string(), trace.filename.clone());
 	parameters.insert("line".to_string(), trace.fileline.to_string());
+	parameters.insert("column".to_string(), trace.fileColumn.to_string());
+	parameters.insert("module".to_string(), trace.modulePath.to_string());
+	parameters.insert("func".to_string(), trace.funcName.to_string());
+	parameters.insert("sequence".to_string(), trace.sequence.to_string());
+	parameters.insert("uptime".to_string(), format!("{:.6}", trace.uptime.as_secs_f64()));
+	parameters.insert("delta".to_string(), trace.delta.map(|delta| format!("{:.6}", delta.as_secs_f64())).unwrap_or_default());
+	parameters.insert("span_duration".to_string(), trace.spanDuration.map(|duration| format!("{:.6}", duration.as_secs_f64())).unwrap_or_default());
+	parameters.insert("span_id".to_string(), trace.context.spanId_get().map(|id| id.to_string()).unwrap_or_default());
+	let mut spanPath = String::new();
+	let _ = FormaterValue::SpanPath(trace.context.spanPath_get()).write(&mut spanPath);
+	parameters.insert("span_path".to_string(), spanPath);
 
 	trace.context.extra_getAll().iter().for_each(|(key,data)|{
 		parameters.insert(format!("extra:{}",key), data.to_string());
 	});
 
 	parameters.insert("msg".to_string(), msg);
+	parameters.insert("backtrace".to_string(), drawBacktraces);
 	return parameters;
-}
\ No newline at end of file
+}
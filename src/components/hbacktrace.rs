@@ -1,4 +1,11 @@
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::collections::HashSet;
+use std::ffi::c_void;
 use std::fmt::{Display, Formatter};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, OnceLock};
+use dashmap::DashMap;
+use crate::components::backtrace_policy::BacktracePolicy;
 
 pub struct Hbacktrace
 {
@@ -7,6 +14,168 @@ pub struct Hbacktrace
 	pub line: Option<u32>,
 }
 
+impl Hbacktrace
+{
+	/// frames of a std backtrace (like the one captured by anyhow::Error), empty if it was not captured
+	/// frames of rustc sources and of anyhow are ignored
+	pub fn fromStd(backtrace: &Backtrace) -> Vec<Hbacktrace>
+	{
+		if(backtrace.status() != BacktraceStatus::Captured)
+		{
+			return vec![];
+		}
+
+		// std only give access to frames through its Display : "  12: crate::func" then "             at ./src/file.rs:42:5"
+		let mut frames: Vec<Hbacktrace> = vec![];
+		for line in backtrace.to_string().lines()
+		{
+			let line = line.trim();
+			if let Some(location) = line.strip_prefix("at ")
+			{
+				if let Some(last) = frames.last_mut() && last.fileName.is_none()
+				{
+					let mut parts = location.rsplitn(3, ':');
+					let _column = parts.next();
+					last.line = parts.next().and_then(|line| line.parse().ok());
+					last.fileName = parts.next().map(|file| file.to_string());
+				}
+				continue;
+			}
+
+			if let Some((index, name)) = line.split_once(": ") && index.parse::<u32>().is_ok()
+			{
+				frames.push(Hbacktrace {
+					funcName: format!("{}()", name),
+					fileName: None,
+					line: None,
+				});
+			}
+		}
+
+		frames.retain(|frame| {
+			!frame.funcName.starts_with("anyhow::") && !frame.funcName.starts_with("<anyhow::")
+				&& !frame.fileName.as_ref().is_some_and(|file| file.starts_with("/rustc/"))
+		});
+		return frames;
+	}
+}
+
+/// backtrace captured without resolving its symbols (which is slow), resolved by the writer thread before the trace is emitted
+pub struct HbacktraceUnresolved
+{
+	_backtrace: backtrace::Backtrace,
+	_policy: BacktracePolicy,
+}
+
+impl HbacktraceUnresolved
+{
+	/// capture the backtrace of the caller, the frames are kept according to the policy when resolved
+	pub fn capture(policy: BacktracePolicy) -> Self
+	{
+		return HbacktraceUnresolved {
+			_backtrace: backtrace::Backtrace::new_unresolved(),
+			_policy: policy,
+		};
+	}
+
+	/// resolve the symbols of the frames, keeping the frames of the caller according to the backtrace policy
+	/// symbols are cached by instruction pointer, so each call site is only resolved once
+	pub fn resolve(&self) -> Vec<Hbacktrace>
+	{
+		let policy = &self._policy;
+		let mut started = false;
+		let mut seen = HashSet::new();
+		let mut returning = Vec::new();
+
+		'frames: for frame in self._backtrace.frames()
+		{
+			// a frame can have multiple symbols (inlined functions), or none if it's not solvable
+			for symbol in symbols_get(frame.ip()).iter()
+			{
+				// the first frames are the capturing ones (Htrace, bridges, ...)
+				if(!started)
+				{
+					if(policy.isStart(&symbol.nameShort))
+					{
+						continue;
+					}
+					started = true;
+				}
+
+				let isNew = !policy.dedupe || seen.insert(symbol.hash);
+				if(isNew && policy.isKept(&symbol.nameShort, symbol.fileName.as_deref()))
+				{
+					returning.push(Hbacktrace {
+						funcName: policy.demangle.name(&symbol.name, &symbol.nameShort),
+						fileName: symbol.fileName.clone(),
+						line: symbol.line,
+					});
+					if(policy.maxFrames.is_some_and(|max| returning.len() >= max))
+					{
+						break 'frames;
+					}
+				}
+
+				if(policy.isStop(&symbol.nameShort))
+				{
+					break 'frames;
+				}
+			}
+		}
+
+		return returning;
+	}
+}
+
+/// resolved symbol of an instruction pointer
+struct ResolvedSymbol
+{
+	/// demangled name, with hash
+	name: String,
+	/// demangled name, without hash
+	nameShort: String,
+	fileName: Option<String>,
+	line: Option<u32>,
+	/// hash of the name, file and line, to find the same frame (see BacktracePolicy::dedupe)
+	hash: u64,
+}
+
+/// resolved symbols by instruction pointer (multiple symbols for inlined functions)
+static SYMBOLS: OnceLock<DashMap<usize, Arc<[ResolvedSymbol]>>> = OnceLock::new();
+
+/// symbols of an instruction pointer, from the cache or resolved
+fn symbols_get(ip: *mut c_void) -> Arc<[ResolvedSymbol]>
+{
+	let cache = SYMBOLS.get_or_init(DashMap::new);
+	if let Some(symbols) = cache.get(&(ip as usize))
+	{
+		return symbols.clone();
+	}
+
+	let mut symbols = vec![];
+	backtrace::resolve(ip, |symbol| {
+		// symbols without name are not solvable
+		let Some(symbolName) = symbol.name() else {return;};
+		let name = symbolName.to_string();
+		let fileName = symbol.filename().map(|x| x.to_string_lossy().to_string());
+		let line = symbol.lineno();
+
+		let mut hasher = DefaultHasher::new();
+		(&name, &fileName, line).hash(&mut hasher);
+		symbols.push(ResolvedSymbol {
+			nameShort: format!("{:#}", symbolName),
+			name,
+			fileName,
+			line,
+			hash: hasher.finish(),
+		});
+	});
+
+	let symbols: Arc<[ResolvedSymbol]> = symbols.into();
+	cache.insert(ip as usize, symbols.clone());
+	return symbols;
+}
+
 impl Display for Hbacktrace
 {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -0,0 +1,97 @@
+use std::future::Future;
+use std::mem::ManuallyDrop;
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+use crate::components::context::Context;
+use crate::context_manager::ContextManager;
+
+/// Future wrapper carrying its own context stack.
+/// The stack is installed on the polling thread during each poll (and during drop), then removed,
+/// so contexts (including "Spaned!" ones created inside the future) follow the task whatever the runtime or worker thread.
+pub struct Instrumented<F>
+{
+	_inner: ManuallyDrop<F>,
+	_stack: Vec<Context>,
+}
+
+impl<F> Instrumented<F>
+{
+	/// run a function with the future's context stack installed on the current thread
+	fn with_stack<R>(stack: &mut Vec<Context>, func: impl FnOnce() -> R) -> R
+	{
+		// restore the thread stack even if func panic
+		struct Restore<'a>
+		{
+			stack: &'a mut Vec<Context>,
+			previous: Vec<Context>,
+		}
+		impl Drop for Restore<'_>
+		{
+			fn drop(&mut self) {
+				*self.stack = ContextManager::singleton().stack_swap(std::mem::take(&mut self.previous));
+			}
+		}
+
+		let previous = ContextManager::singleton().stack_swap(std::mem::take(stack));
+		let _restore = Restore { stack, previous };
+		return func();
+	}
+}
+
+impl<F: Future> Future for Instrumented<F>
+{
+	type Output = F::Output;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+		// SAFETY: the inner future is never moved out of self, it is only dropped in place (see Drop)
+		let this = unsafe { self.get_unchecked_mut() };
+		let inner = unsafe { Pin::new_unchecked(&mut *this._inner) };
+		return Self::with_stack(&mut this._stack, || inner.poll(cx));
+	}
+}
+
+impl<F> Drop for Instrumented<F>
+{
+	fn drop(&mut self) {
+		// spans living inside the future are dropped with the future's stack installed
+		let inner = &mut self._inner;
+		Self::with_stack(&mut self._stack, || {
+			// SAFETY: _inner is never used again after this
+			unsafe { ManuallyDrop::drop(inner) };
+		});
+	}
+}
+
+/// Extension for futures, making a context follow the task across threads (see Instrumented)
+/// ```
+/// use Htrace::components::instrument::HtraceFutureExt;
+/// use Htrace::HTrace;
+///
+/// let task = async {
+///     HTrace!("inside the task context");
+/// }.with_htrace_context("my task");
+/// ```
+pub trait HtraceFutureExt: Future + Sized
+{
+	/// wrap the future with the current thread context stack, plus a new context on top of it
+	fn with_htrace_context(self, context: impl Into<Context>) -> Instrumented<Self>
+	{
+		let mut stack = ContextManager::singleton().stack_get();
+		stack.push(context.into());
+		return Instrumented {
+			_inner: ManuallyDrop::new(self),
+			_stack: stack,
+		};
+	}
+
+	/// wrap the future with the current thread context stack
+	fn with_current_htrace_context(self) -> Instrumented<Self>
+	{
+		return Instrumented {
+			_inner: ManuallyDrop::new(self),
+			_stack: ContextManager::singleton().stack_get(),
+		};
+	}
+}
+
+impl<F: Future> HtraceFutureExt for F {}
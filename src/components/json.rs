@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// minimal json builder, used by modules that need to send json without depending on serde
+/// keys are written in insertion order
+pub(crate) struct JsonObject
+{
+	_content: String,
+}
+
+impl JsonObject
+{
+	pub fn new() -> Self
+	{
+		return JsonObject {
+			_content: String::new(),
+		};
+	}
+
+	/// add a string value
+	pub fn string(&mut self, key: &str, value: &str) -> &mut Self
+	{
+		self.key(key);
+		escapeInto(&mut self._content, value);
+		return self;
+	}
+
+	/// add a numeric value (non-finite number are written as null)
+	pub fn number(&mut self, key: &str, value: f64) -> &mut Self
+	{
+		self.key(key);
+		if(value.is_finite())
+		{
+			let _ = write!(self._content, "{}", value);
+		}
+		else
+		{
+			self._content.push_str("null");
+		}
+		return self;
+	}
+
+	/// add an array of string
+	pub fn stringArray<'a>(&mut self, key: &str, values: impl IntoIterator<Item = &'a str>) -> &mut Self
+	{
+		self.key(key);
+		self._content.push('[');
+		for (i, value) in values.into_iter().enumerate()
+		{
+			if(i > 0)
+			{
+				self._content.push(',');
+			}
+			escapeInto(&mut self._content, value);
+		}
+		self._content.push(']');
+		return self;
+	}
+
+	/// add an object of string values
+	pub fn stringMap(&mut self, key: &str, values: &HashMap<String, String>) -> &mut Self
+	{
+		let mut inner = JsonObject::new();
+		let mut keys = values.keys().collect::<Vec<_>>();
+		keys.sort();
+		for oneKey in keys
+		{
+			inner.string(oneKey, &values[oneKey]);
+		}
+		self.key(key);
+		self._content.push_str(&inner.build());
+		return self;
+	}
+
+	/// return the json string
+	pub fn build(&self) -> String
+	{
+		return format!("{{{}}}", self._content);
+	}
+
+	fn key(&mut self, key: &str)
+	{
+		if(!self._content.is_empty())
+		{
+			self._content.push(',');
+		}
+		escapeInto(&mut self._content, key);
+		self._content.push(':');
+	}
+}
+
+/// write a json escaped string (with its quotes)
+pub(crate) fn escapeInto(output: &mut String, content: &str)
+{
+	output.push('"');
+	for c in content.chars()
+	{
+		match c
+		{
+			'"' => output.push_str("\\\""),
+			'\\' => output.push_str("\\\\"),
+			'\n' => output.push_str("\\n"),
+			'\r' => output.push_str("\\r"),
+			'\t' => output.push_str("\\t"),
+			c if (c as u32) < 0x20 => {
+				let _ = write!(output, "\\u{:04x}", c as u32);
+			}
+			c => output.push(c),
+		}
+	}
+	output.push('"');
+}
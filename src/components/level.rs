@@ -49,17 +49,23 @@ impl Level
 	}
 	
 	pub fn convert4LengthString(&self) -> String
+	{
+		return self.as4LengthStr().to_string();
+	}
+
+	/// same as convert4LengthString, without allocation
+	pub fn as4LengthStr(&self) -> &'static str
 	{
 		match *self
 		{
-			Level::DEBUG => "DBUG".to_string(),
-			Level::DEBUGERR => "ERRD".to_string(),
-			Level::ERROR => "ERR ".to_string(),
-			Level::FATAL => "FATA".to_string(),
-			Level::NOTICE => "NOTI".to_string(),
-			Level::NOTICEDERR => "NOER".to_string(),
-			Level::WARNING => "WARN".to_string(),
-			Level::NORMAL => "    ".to_string()
+			Level::DEBUG => "DBUG",
+			Level::DEBUGERR => "ERRD",
+			Level::ERROR => "ERR ",
+			Level::FATAL => "FATA",
+			Level::NOTICE => "NOTI",
+			Level::NOTICEDERR => "NOER",
+			Level::WARNING => "WARN",
+			Level::NORMAL => "    "
 		}
 	}
 	
@@ -0,0 +1,99 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter, Write};
+
+/// conversion of a traced value into the trace message (see HTracer::trace_at)
+/// implemented for every Display type (String, &str, Cow<str>, fmt::Arguments, numbers, ...)
+/// wrap errors with ErrorChain to render their causes, and Debug only types with DebugMessage (HTrace! does it automatically)
+pub trait IntoTraceMessage
+{
+	/// write the message at the end of output
+	fn message_write(&self, output: &mut String);
+
+	/// the message as a new string
+	fn message_get(&self) -> String
+	{
+		let mut output = String::new();
+		self.message_write(&mut output);
+		return output;
+	}
+}
+
+impl<T: Display + ?Sized> IntoTraceMessage for T
+{
+	fn message_write(&self, output: &mut String)
+	{
+		// fmt::Arguments (format_args!) are written directly, without an intermediate string
+		let _ = write!(output, "{}", self);
+	}
+}
+
+/// render an error with its causes (Error::source() chain) : "error : cause : root cause"
+pub struct ErrorChain<'a>(pub &'a (dyn Error + 'a));
+
+impl Display for ErrorChain<'_>
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.0)?;
+		let mut source = self.0.source();
+		while let Some(cause) = source
+		{
+			write!(f, " : {}", cause)?;
+			source = cause.source();
+		}
+		return Ok(());
+	}
+}
+
+/// render a value with its Debug implementation
+pub struct DebugMessage<'a, T: ?Sized>(pub &'a T);
+
+impl<T: Debug + ?Sized> Display for DebugMessage<'_, T>
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		return write!(f, "{:?}", self.0);
+	}
+}
+
+/// used by HTrace! to choose the message of a value (autoref specialization):
+/// errors are rendered with their causes (ErrorChain), then Display types, then Debug types (DebugMessage)
+#[doc(hidden)]
+pub struct MessageSelect<'a, T: ?Sized>(pub &'a T);
+
+#[doc(hidden)]
+pub trait MessageSelectError<'a>
+{
+	fn message_select(&self) -> ErrorChain<'a>;
+}
+
+impl<'a, T: Error + 'a> MessageSelectError<'a> for &&MessageSelect<'a, T>
+{
+	fn message_select(&self) -> ErrorChain<'a> {
+		return ErrorChain(self.0);
+	}
+}
+
+#[doc(hidden)]
+pub trait MessageSelectDisplay<'a, T: ?Sized>
+{
+	fn message_select(&self) -> &'a T;
+}
+
+impl<'a, T: Display + ?Sized> MessageSelectDisplay<'a, T> for &MessageSelect<'a, T>
+{
+	fn message_select(&self) -> &'a T {
+		return self.0;
+	}
+}
+
+#[doc(hidden)]
+pub trait MessageSelectDebug<'a, T: ?Sized>
+{
+	fn message_select(&self) -> DebugMessage<'a, T>;
+}
+
+impl<'a, T: Debug + ?Sized> MessageSelectDebug<'a, T> for MessageSelect<'a, T>
+{
+	fn message_select(&self) -> DebugMessage<'a, T> {
+		return DebugMessage(self.0);
+	}
+}
@@ -1,6 +1,13 @@
 pub mod span;
 pub mod hbacktrace;
+pub mod backtrace_policy;
+pub mod rate_limit;
+pub mod sampling;
 pub mod level;
 pub mod trace;
+pub mod message;
+pub mod error;
 pub mod context;
-pub mod formater; // (crate)
\ No newline at end of file
+pub mod formater; // (crate)
+pub mod instrument;
+pub(crate) mod json;
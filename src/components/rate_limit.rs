@@ -0,0 +1,166 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use dashmap::DashMap;
+use crate::components::context::Context;
+use crate::components::level::Level;
+use crate::components::trace::TraceSource;
+
+/// how many traces of a same call site are emitted (see Context::rateLimit_set)
+/// a call site is identified by its file, line, column, level and format string
+/// the first maxPerWindow traces of a window are emitted, the next ones are only counted,
+/// and a summary trace ("repeated 4812 times in 10s") is emitted at the end of the window
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit
+{
+	/// traces of a call site emitted per window (default 10, None to disable)
+	pub maxPerWindow: Option<u32>,
+	/// duration of a window, starting at the first trace of the call site (default 10s)
+	pub window: Duration,
+}
+
+impl Default for RateLimit
+{
+	fn default() -> Self {
+		return RateLimit {
+			maxPerWindow: Some(10),
+			window: Duration::from_secs(10),
+		};
+	}
+}
+
+/// suppressed traces of a call site, at the end of its window
+pub(crate) struct RateLimitSummary
+{
+	pub repeated: u64,
+	pub duration: Duration,
+	pub level: Level,
+	/// context of the first suppressed trace
+	pub context: Arc<Context>,
+	pub filename: String,
+	pub fileline: u32,
+	pub fileColumn: u32,
+	pub modulePath: &'static str,
+	pub funcName: &'static str,
+}
+
+impl RateLimitSummary
+{
+	pub fn message_get(&self) -> String
+	{
+		return format!("repeated {} times in {:?}", self.repeated, self.duration);
+	}
+}
+
+pub(crate) enum RateLimitCheck
+{
+	Emit,
+	Suppress,
+	/// the previous window of the call site is over, its summary is emitted before the trace
+	EmitAfter(RateLimitSummary),
+}
+
+/// current window of a call site
+struct RateLimitSite
+{
+	start: Instant,
+	window: Duration,
+	count: u32,
+	summary: Option<RateLimitSummary>,
+}
+
+impl RateLimitSite
+{
+	fn new(limit: &RateLimit) -> Self
+	{
+		return RateLimitSite {
+			start: Instant::now(),
+			window: limit.window,
+			count: 0,
+			summary: None,
+		};
+	}
+
+	fn isExpired(&self, now: Instant) -> bool
+	{
+		return now.duration_since(self.start) >= self.window;
+	}
+}
+
+/// windows of the rate limited call sites
+pub(crate) struct RateLimiter
+{
+	_sites: DashMap<u64, RateLimitSite>,
+}
+
+impl RateLimiter
+{
+	pub fn new() -> Self
+	{
+		return RateLimiter {
+			_sites: DashMap::new(),
+		};
+	}
+
+	/// count a trace of a call site, and decide if it's emitted
+	pub fn check(&self, limit: &RateLimit, level: Level, source: &TraceSource, context: &Arc<Context>) -> RateLimitCheck
+	{
+		let Some(max) = limit.maxPerWindow else {
+			return RateLimitCheck::Emit;
+		};
+
+		let mut hasher = DefaultHasher::new();
+		(source.file, source.line, source.column, level.tou8(), source.template).hash(&mut hasher);
+		let mut site = self._sites.entry(hasher.finish()).or_insert_with(|| RateLimitSite::new(limit));
+
+		let mut previous = None;
+		if(site.isExpired(Instant::now()))
+		{
+			previous = site.summary.take();
+			*site = RateLimitSite::new(limit);
+		}
+
+		site.count = site.count.saturating_add(1);
+		if(site.count <= max)
+		{
+			return match previous {
+				None => RateLimitCheck::Emit,
+				Some(summary) => RateLimitCheck::EmitAfter(summary),
+			};
+		}
+
+		let duration = site.window;
+		site.summary.get_or_insert_with(|| RateLimitSummary {
+			repeated: 0,
+			duration,
+			level,
+			context: context.clone(),
+			filename: source.file.to_string(),
+			fileline: source.line,
+			fileColumn: source.column,
+			modulePath: source.module,
+			funcName: source.func,
+		}).repeated += 1;
+		return RateLimitCheck::Suppress;
+	}
+
+	/// remove the call sites whose window is over (all of them if force), returning the summaries of their suppressed traces
+	pub fn expired(&self, force: bool) -> Vec<RateLimitSummary>
+	{
+		let now = Instant::now();
+		let mut summaries = vec![];
+		self._sites.retain(|_, site| {
+			if(!force && !site.isExpired(now))
+			{
+				return true;
+			}
+			if let Some(mut summary) = site.summary.take()
+			{
+				summary.duration = now.duration_since(site.start).min(site.window);
+				summaries.push(summary);
+			}
+			return false;
+		});
+		return summaries;
+	}
+}
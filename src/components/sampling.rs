@@ -0,0 +1,147 @@
+use std::cell::Cell;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::components::level::Level;
+
+/// how many traces matching a sampling rule are kept
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleRate
+{
+	/// each trace is kept with this probability (between 0 and 1)
+	Probability(f64),
+	/// one trace every N is kept (the first one, then the N+1th, ...)
+	EveryNth(u32),
+}
+
+impl SampleRate
+{
+	/// proportion of the traces kept, between 0 and 1 (see OneTrace.sampleRate)
+	pub fn rate(&self) -> f64
+	{
+		return match self {
+			SampleRate::Probability(probability) => probability.clamp(0.0, 1.0),
+			SampleRate::EveryNth(n) => 1.0 / (*n).max(1) as f64,
+		};
+	}
+}
+
+/// traces sampled at a rate, matching all the given criteria (see Sampling)
+/// ```
+/// use Htrace::components::level::Level;
+/// use Htrace::components::sampling::{SampleRate, SamplingRule};
+///
+/// let rule = SamplingRule::new(SampleRate::EveryNth(100)).level(Level::DEBUG).file("src/network.rs");
+/// ```
+#[derive(Debug, Clone)]
+pub struct SamplingRule
+{
+	_rate: SampleRate,
+	_level: Option<Level>,
+	_file: Option<String>,
+	_context: Option<String>,
+	/// traces matching this rule, shared by the copies of the rule (resolved contexts)
+	_counter: Arc<AtomicU64>,
+}
+
+impl SamplingRule
+{
+	/// rule matching any trace
+	pub fn new(rate: SampleRate) -> Self
+	{
+		return SamplingRule {
+			_rate: rate,
+			_level: None,
+			_file: None,
+			_context: None,
+			_counter: Arc::new(AtomicU64::new(0)),
+		};
+	}
+
+	/// only match traces of this level
+	pub fn level(mut self, level: Level) -> Self
+	{
+		self._level = Some(level);
+		return self;
+	}
+
+	/// only match traces of a file ending with this path, like "src/network.rs"
+	pub fn file(mut self, file: impl Into<String>) -> Self
+	{
+		self._file = Some(file.into());
+		return self;
+	}
+
+	/// only match traces of a context with this name (the resolved one, see Context::name_set)
+	pub fn context(mut self, name: impl Into<String>) -> Self
+	{
+		self._context = Some(name.into());
+		return self;
+	}
+
+	pub fn rate_get(&self) -> SampleRate
+	{
+		return self._rate;
+	}
+
+	/// the rule applies to this trace
+	pub fn isMatching(&self, level: Level, file: &str, context: Option<&str>) -> bool
+	{
+		return self._level.is_none_or(|one| one == level)
+			&& self._file.as_ref().is_none_or(|one| file.ends_with(one.as_str()))
+			&& self._context.as_ref().is_none_or(|one| context == Some(one.as_str()));
+	}
+
+	/// decide if a matching trace is kept
+	pub fn isSampled(&self) -> bool
+	{
+		return match self._rate {
+			SampleRate::Probability(probability) => random() < probability,
+			SampleRate::EveryNth(n) => self._counter.fetch_add(1, Ordering::Relaxed).is_multiple_of(n.max(1) as u64),
+		};
+	}
+}
+
+/// which traces are kept for high volume levels (see Context::sampling_set)
+/// the first rule matching a trace decides, traces matching no rule are all kept
+/// sampling is decided before the message is formatted, and the rate is stored in OneTrace.sampleRate
+#[derive(Debug, Clone, Default)]
+pub struct Sampling
+{
+	pub rules: Vec<SamplingRule>,
+}
+
+impl Sampling
+{
+	/// None if the trace is dropped, else the rate of its rule (1 if no rule matches)
+	pub fn sample(&self, level: Level, file: &str, context: Option<&str>) -> Option<f64>
+	{
+		let Some(rule) = self.rules.iter().find(|rule| rule.isMatching(level, file, context)) else {
+			return Some(1.0);
+		};
+
+		if(!rule.isSampled())
+		{
+			return None;
+		}
+		return Some(rule.rate_get().rate());
+	}
+}
+
+thread_local! {
+	/// xorshift state of the thread, randomly seeded
+	static RANDOM: Cell<u64> = Cell::new(RandomState::new().hash_one(0u64) | 1);
+}
+
+/// random number between 0 and 1
+fn random() -> f64
+{
+	return RANDOM.with(|state| {
+		let mut x = state.get();
+		x ^= x << 13;
+		x ^= x >> 7;
+		x ^= x << 17;
+		state.set(x);
+		(x >> 11) as f64 / (1u64 << 53) as f64
+	});
+}
@@ -1,26 +1,120 @@
+use std::fmt::{Display, Formatter};
+use std::panic::Location;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use crate::components::context::Context;
+use crate::components::trace::TraceSource;
 use crate::context_manager::ContextManager;
+use crate::htracer::HTracer;
+
+/// event of a span, carried by the traces given to ModuleAbstract::event_onSpanEnter / event_onSpanExit
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SpanEvent
+{
+	Enter,
+	Exit,
+}
+
+/// one span of the span path of a trace, from the root span to the closest one (see Context::spanPath_get)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SpanInfo
+{
+	/// unique id of the span
+	pub id: u64,
+	/// name of the span context (empty if it has none)
+	pub name: String,
+}
+
+impl Display for SpanInfo
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}#{}", self.name, self.id)
+	}
+}
+
+static SPANID: AtomicU64 = AtomicU64::new(1);
 
 /// "short" lived structure, used to define the lifetime of a context
+/// each span has a unique id, traces know the path of their spans (see Context::spanPath_get)
+/// modules are notified when the span is entered and exited (see ModuleAbstract::event_onSpanEnter),
+/// and enter/exit traces can be emitted (see Context::spanTraces_set)
 pub struct Span
 {
-	_contextId: u64
+	_id: u64,
+	_contextId: u64,
+	_start: Instant,
+	/// where the span was created, used as the source of its events
+	_source: TraceSource<'static>,
+	/// the exit event is emitted on drop
+	_exitEvent: bool,
 }
 
 impl Span
 {
+	#[track_caller]
 	pub fn new(context: Context) -> Self
 	{
-		let id = ContextManager::singleton().add(context);
-		Self {
-			_contextId: id
+		let location = Location::caller();
+		return Self::create(context, Self::id_next(), Instant::now(), TraceSource {
+			file: location.file(),
+			line: location.line(),
+			column: location.column(),
+			..Default::default()
+		}, true, true);
+	}
+
+	/// unique id of the span
+	pub fn id_get(&self) -> u64
+	{
+		return self._id;
+	}
+
+	/// time since the span was entered
+	pub fn elapsed(&self) -> Duration
+	{
+		return self._start.elapsed();
+	}
+
+	/// span of another crate, installed by the bridges each time it is entered (with its id, start and source)
+	/// its enter/exit events are only emitted when asked, once per span of the other crate
+	pub(crate) fn bridged(context: Context, id: u64, start: Instant, source: TraceSource<'static>, enterEvent: bool, exitEvent: bool) -> Self
+	{
+		return Self::create(context, id, start, source, enterEvent, exitEvent);
+	}
+
+	/// a new unique span id
+	pub(crate) fn id_next() -> u64
+	{
+		return SPANID.fetch_add(1, Ordering::Relaxed);
+	}
+
+	///////////// PRIVATE ///////////
+
+	fn create(mut context: Context, id: u64, start: Instant, source: TraceSource<'static>, enterEvent: bool, exitEvent: bool) -> Self
+	{
+		context.span_set(id, start);
+		let span = Span {
+			_id: id,
+			_contextId: ContextManager::singleton().add(context),
+			_start: start,
+			_source: source,
+			_exitEvent: exitEvent,
+		};
+		if(enterEvent)
+		{
+			HTracer::span_event(SpanEvent::Enter, Duration::ZERO, span._source);
 		}
+		return span;
 	}
 }
 
 impl Drop for Span
 {
 	fn drop(&mut self) {
+		if(self._exitEvent)
+		{
+			HTracer::span_event(SpanEvent::Exit, self.elapsed(), self._source);
+		}
 		ContextManager::singleton().remove(self._contextId);
 	}
 }
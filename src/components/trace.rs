@@ -1,28 +1,128 @@
+use std::sync::Arc;
+use std::time::Duration;
 use time::OffsetDateTime;
-use crate::components::hbacktrace::Hbacktrace;
+use crate::components::hbacktrace::{Hbacktrace, HbacktraceUnresolved};
 use crate::components::context::Context;
 use crate::components::level::Level;
+use crate::components::span::SpanEvent;
 
 pub struct OneTrace
 {
 	pub message: String,
 	pub date: OffsetDateTime,
 	pub level: Level,
-	pub context: Context,
+	/// resolved context of the trace, shared by the traces of a thread while its contexts don't change
+	pub context: Arc<Context>,
 	pub filename: String,
 	pub fileline: u32,
-	pub backtraces: Vec<Hbacktrace>
+	/// column of the trace in the file (0 if unknown)
+	pub fileColumn: u32,
+	/// module path of the trace, like "mycrate::network" (empty if unknown)
+	pub modulePath: &'static str,
+	/// function emitting the trace, relative to modulePath, like "connect" or "Client::connect" (empty if unknown)
+	pub funcName: &'static str,
+	pub backtraces: Vec<Hbacktrace>,
+	/// backtrace captured with the trace, resolved into backtraces by the writer thread (see backtrace_resolve)
+	pub backtraceUnresolved: Option<HbacktraceUnresolved>,
+	/// causes of a traced error (its Error::source() chain), from the closest to the root one (see HTracer::trace_error)
+	pub causes: Vec<String>,
+	/// global order of creation, used to keep the order of traces with the same date
+	pub sequence: u64,
+	/// monotonic time since HTracer::globalContext_set()
+	pub uptime: Duration,
+	/// monotonic time since the previous trace of the same thread (None for the first one)
+	pub delta: Option<Duration>,
+	/// proportion of the similar traces that are kept, 1 if the trace is not sampled (see Sampling)
+	pub sampleRate: f64,
+	/// time since the closest span was entered, its full duration for its exit (None outside of spans)
+	pub spanDuration: Option<Duration>,
+	/// set for the traces notifying a span enter/exit, given to ModuleAbstract::event_onSpanEnter / event_onSpanExit instead of the level event
+	pub spanEvent: Option<SpanEvent>,
 }
 
 impl OneTrace
 {
+	/// resolve the symbols of backtraceUnresolved (if any) into backtraces
+	pub fn backtrace_resolve(&mut self)
+	{
+		if let Some(unresolved) = self.backtraceUnresolved.take()
+		{
+			self.backtraces = unresolved.resolve();
+		}
+	}
+
 	pub fn emit(&self)
 	{
 		self.context.modules_get().iter().for_each(|(_,module)| {
 			if let Some(inner) = module
 			{
-				Level::launchModuleFunc(inner,self);
+				match self.spanEvent {
+					None => Level::launchModuleFunc(inner,self),
+					Some(SpanEvent::Enter) => inner.event_onSpanEnter(self),
+					Some(SpanEvent::Exit) => {
+						inner.event_onSpanExit(self);
+						inner.event_onContextExit(&self.context);
+					}
+				}
 			}
 		})
 	}
 }
+
+impl Default for OneTrace
+{
+	fn default() -> Self {
+		return OneTrace {
+			message: String::new(),
+			date: OffsetDateTime::UNIX_EPOCH,
+			level: Level::NORMAL,
+			context: Arc::new(Context::default()),
+			filename: String::new(),
+			fileline: 0,
+			fileColumn: 0,
+			modulePath: "",
+			funcName: "",
+			backtraces: vec![],
+			backtraceUnresolved: None,
+			causes: vec![],
+			sequence: 0,
+			uptime: Duration::ZERO,
+			delta: None,
+			sampleRate: 1.0,
+			spanDuration: None,
+			spanEvent: None,
+		};
+	}
+}
+
+/// location in the source code where a trace is emitted (see HTraceSource!() and HTracer::trace_at)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceSource<'a>
+{
+	pub file: &'a str,
+	pub line: u32,
+	/// 0 if unknown
+	pub column: u32,
+	/// empty if unknown
+	pub module: &'static str,
+	/// empty if unknown
+	pub func: &'static str,
+	/// format string of the trace, used to identify the call site (see RateLimit), empty if unknown
+	pub template: &'static str,
+}
+
+impl TraceSource<'_>
+{
+	/// clean the type name of a marker function defined inside the traced function (see HTraceSource!())
+	/// remove the marker name, closures, and the module path
+	pub fn functionName(markerTypeName: &'static str, module: &str) -> &'static str
+	{
+		let mut name = markerTypeName.strip_suffix("::htrace_source_marker").unwrap_or(markerTypeName);
+		while let Some(parent) = name.strip_suffix("::{{closure}}")
+		{
+			name = parent;
+		}
+
+		return name.strip_prefix(module).and_then(|x| x.strip_prefix("::")).unwrap_or(name);
+	}
+}
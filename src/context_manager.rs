@@ -1,160 +1,230 @@
 use crate::components::context::Context;
+use crate::components::level::Level;
+use crate::components::span::SpanInfo;
 use crate::thread_manager::{ThreadManager, MAIN_THREAD};
-use dashmap::DashMap;
-use std::sync::OnceLock;
-
+use parking_lot::RwLock;
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// store the global context, and a context stack per thread
+/// stacks are thread local: each thread (named or not) have its own stack, freed when the thread exit
+/// each thread cache its resolved context, until its stack, its extras/name or the global context change
 pub(crate) struct ContextManager
 {
-	data: DashMap<String, Vec<Context>>,
+	global: RwLock<Option<Context>>,
+	/// incremented each time the global context change, invalidating thread caches
+	generation: AtomicU64,
 }
 
 static SINGLETON: OnceLock<ContextManager> = OnceLock::new();
 
 impl ContextManager
 {
+	thread_local!{
+		static STACK: RefCell<Vec<Context>> = RefCell::new(Vec::new());
+		/// effective minimum level of this thread, with the generation it was computed for (see level_getMin)
+		static MINLEVEL: Cell<Option<(u64, Level)>> = const { Cell::new(None) };
+		/// resolved context of this thread, with the generation it was computed for (see resolve)
+		static RESOLVED: RefCell<Option<(u64, Arc<Context>)>> = const { RefCell::new(None) };
+	}
+
 	/// get singleton
 	pub fn singleton() -> &'static Self
 	{
 		return SINGLETON.get_or_init(|| Self::new());
 	}
 
+	/// push a context on the current thread stack, return its position (used by remove())
 	pub fn add(&self, context: Context) -> u64
 	{
-		let threadName = ThreadManager::local_getName().unwrap_or(MAIN_THREAD.to_string());
-		return match self.data.get_mut(&threadName)
-		{
-			None =>
-			{
-				self.data.insert(threadName, vec![context.clone()]);
-				0
-			}
-			Some(mut inner) =>
-			{
-				let size = inner.len();
-				inner.push(context.clone());
-				size as u64
-			}
-		};
+		Self::cache_invalidate();
+		return Self::STACK.try_with(|stack| {
+			let mut stack = stack.borrow_mut();
+			stack.push(context);
+			(stack.len() - 1) as u64
+		}).unwrap_or(0);
 	}
 
 	pub fn remove(&self, contextId: u64)
 	{
-		let threadName = ThreadManager::local_getName().unwrap_or(MAIN_THREAD.to_string());
-		if let Some(mut inner) = self.data.get_mut(&threadName)
-		{
-			if(inner.iter().len()==1 && threadName==MAIN_THREAD)
-			{
-				return;
-			}
+		Self::cache_invalidate();
+		let _ = Self::STACK.try_with(|stack| {
 			// remove last element until the context is removed (normally it's always the last, but this is resilient of something wrong happened)
-			while (contextId < inner.iter().len() as u64)
-			{
-				inner.pop();
-			}
-		}
+			stack.borrow_mut().truncate(contextId as usize);
+		});
+	}
+
+	/// replace the context stack of the current thread, and return the previous one
+	/// used to make a context stack follow a task (see components::instrument)
+	pub(crate) fn stack_swap(&self, stack: Vec<Context>) -> Vec<Context>
+	{
+		Self::cache_invalidate();
+		return Self::STACK.try_with(|current| mem::replace(&mut *current.borrow_mut(), stack)).unwrap_or_default();
+	}
+
+	/// copy of the context stack of the current thread (without the global context)
+	pub(crate) fn stack_get(&self) -> Vec<Context>
+	{
+		return Self::STACK.try_with(|stack| stack.borrow().clone()).unwrap_or_default();
 	}
 
 	/// reset the global context
 	pub(crate) fn global_set(&self, context: Context)
 	{
-		let globalName = MAIN_THREAD.to_string();
-		self.data.insert(globalName,vec![context]);
+		*self.global.write() = Some(context);
+		self.generation.fetch_add(1, Ordering::Release);
 	}
 
 	/// get the global context
 	pub(crate) fn global_get(&self) -> Option<Context>
 	{
-		let globalName = MAIN_THREAD.to_string();
-		match self.data.get(&globalName) {
-			None => None,
-			Some(contextArray) => contextArray.get(0).cloned()
-		}
+		return self.global.read().clone();
 	}
 
-	/// resolve a trace context
-	/// it starts from the global context, go into the current thread, and go down do the last context (the closest one)
-	/// each context add its own information/modules/extras
-	pub fn resolve(&self) -> Context
+	/// effective minimum level of the current thread (global context, then each context of the stack)
+	/// cached until the stack or the global context change, so it's cheap enough to be checked before formatting a trace
+	pub fn level_getMin(&self) -> Level
 	{
-		let Some(threadName) = ThreadManager::local_getName()
-		else
+		let generation = self.generation.load(Ordering::Acquire);
+		if let Ok(Some((cachedGeneration, level))) = Self::MINLEVEL.try_with(|cache| cache.get()) && cachedGeneration == generation
 		{
-			return self.resolve_internal(MAIN_THREAD.to_string());
-		};
+			return level;
+		}
+
+		let mut level = self.global.read().as_ref().and_then(|global| global.level_getMin().copied());
+		let _ = Self::STACK.try_with(|stack| {
+			stack.borrow().iter().for_each(|oneContext| {
+				if let Some(min) = oneContext.level_getMin()
+				{
+					level = Some(*min);
+				}
+			});
+		});
+		let level = level.unwrap_or(Level::DEBUG);
 
-		return self.resolve_internal(threadName);
+		let _ = Self::MINLEVEL.try_with(|cache| cache.set(Some((generation, level))));
+		return level;
 	}
 
-	////////////// PRIVATE ///////////
+	/// resolved context of the current thread (see resolve_uncached), shared by its traces until something change
+	pub fn resolve(&self) -> Arc<Context>
+	{
+		let generation = self.generation.load(Ordering::Acquire);
+		let cached = Self::RESOLVED.try_with(|cache| {
+			cache.borrow().as_ref()
+				.filter(|(cachedGeneration, _)| *cachedGeneration == generation)
+				.map(|(_, context)| context.clone())
+		});
+		if let Ok(Some(context)) = cached
+		{
+			return context;
+		}
 
-	fn new() -> Self
+		let context = Arc::new(self.resolve_uncached());
+		let _ = Self::RESOLVED.try_with(|cache| *cache.borrow_mut() = Some((generation, context.clone())));
+		return context;
+	}
+
+	/// forget the cached data of the current thread (after a change of its stack, extras or name)
+	pub(crate) fn cache_invalidate()
 	{
-		return ContextManager {
-			data: DashMap::new(),
-		};
+		let _ = Self::MINLEVEL.try_with(|cache| cache.set(None));
+		let _ = Self::RESOLVED.try_with(|cache| cache.borrow_mut().take());
 	}
 
-	/// resolve the context
-	fn resolve_internal(&self, threadName: String) -> Context
+	////////////// PRIVATE ///////////
+
+	/// resolve a trace context
+	/// it starts from the global context, go into the current thread, and go down do the last context (the closest one)
+	/// each context add its own information/modules/extras
+	/// extras precedence (last wins): global context, thread extras (see thread::extra_set), then each context of the stack
+	fn resolve_uncached(&self) -> Context
 	{
 		// we always start from the global context
 		let mut resolvedContext = self.resolve_main();
-
-		// if this threadname have no existing context, we're using the global one
-		let Some(contextArray) = self.data.get(&threadName)
-		else
-		{
-			return resolvedContext;
-		};
-
-		// going throw each context
-		contextArray.iter().for_each(|oneContext| {
-			oneContext
-				.modules_get()
-				.iter()
-				.for_each(|(key, context)| {
-					match context
-					{
-						None => resolvedContext.module_discard(key),
-						Some(inner) => resolvedContext.module_add_arc(key, inner.clone()),
-					}
-				});
-			resolvedContext.level_setMin(oneContext.level_getMin().cloned());
-			if let Some(name) = oneContext.threadName_get()
-			{
-				resolvedContext.threadName_set(name);
-			}
-			if let Some(name) = oneContext.name_get()
-			{
-				resolvedContext.name_set(name);
-			}
-			resolvedContext.extra_merge(oneContext.extra_getAll());
+		resolvedContext.extra_merge(&ThreadManager::local_getAllExtraDatas());
+
+		// going throw each context of this thread
+		let _ = Self::STACK.try_with(|stack| {
+			stack.borrow().iter().for_each(|oneContext| {
+				resolvedContext.id_set(oneContext.id_get());
+				oneContext
+					.modules_get()
+					.iter()
+					.for_each(|(key, context)| {
+						match context
+						{
+							None => resolvedContext.module_discard(key),
+							Some(inner) => resolvedContext.module_add_arc(key, inner.clone()),
+						}
+					});
+				if let Some(min) = oneContext.level_getMin()
+				{
+					resolvedContext.level_setMin(Some(*min));
+				}
+				if let Some(offset) = oneContext.timeOffset_get()
+				{
+					resolvedContext.timeOffset_set(Some(*offset));
+				}
+				if let Some(policy) = oneContext.backtracePolicy_get()
+				{
+					resolvedContext.backtracePolicy_set(Some(policy.clone()));
+				}
+				if let Some(limit) = oneContext.rateLimit_get()
+				{
+					resolvedContext.rateLimit_set(Some(*limit));
+				}
+				if let Some(sampling) = oneContext.sampling_get()
+				{
+					resolvedContext.sampling_set(Some(sampling.clone()));
+				}
+				if let Some(level) = oneContext.spanTraces_get()
+				{
+					resolvedContext.spanTraces_set(Some(*level));
+				}
+				if let Some(id) = oneContext.spanId_get() && let Some(start) = oneContext.spanStart_get()
+				{
+					resolvedContext.span_set(id, *start);
+					resolvedContext.spanPath_push(SpanInfo {
+						id,
+						name: oneContext.name_get().clone().unwrap_or_default(),
+					});
+				}
+				if let Some(name) = oneContext.name_get()
+				{
+					resolvedContext.name_set(name);
+				}
+				resolvedContext.extra_merge(oneContext.extra_getAll());
+			});
 		});
 
+		// the thread name is only a display information of the thread emitting the trace
+		resolvedContext.threadName_set(ThreadManager::local_getDisplayName());
+
 		return resolvedContext;
 	}
 
+	fn new() -> Self
+	{
+		return ContextManager {
+			global: RwLock::new(None),
+			generation: AtomicU64::new(0),
+		};
+	}
+
 	/// resolve the main context
 	fn resolve_main(&self) -> Context
 	{
-		let defaultContext = || {
+		let Some(context) = self.global_get()
+		else
+		{
 			let mut context = Context::default();
 			context.threadName_set(MAIN_THREAD);
 			return context;
 		};
 
-		let Some(contextArray) = self.data.get(&MAIN_THREAD.to_string())
-		else
-		{
-			return defaultContext();
-		};
-
-		let Some(context) = contextArray.get(0)
-		else
-		{
-			return defaultContext();
-		};
-
-		return context.clone();
+		return context;
 	}
 }
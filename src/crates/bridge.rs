@@ -1,4 +1,5 @@
 use tracing::Metadata;
+use crate::components::backtrace_policy::BacktracePolicy;
 use crate::components::level::Level;
 
 #[cfg(feature = "tracing_consumer")]
@@ -25,8 +26,11 @@ impl Default for HtraceBridgeSpanFilterList {
 }
 
 #[derive(Clone)]
+/// backtraces of bridged traces follow the backtrace policy of the context (see Context::backtracePolicy_set)
 pub struct HtraceBridge {
-	/// minimum level for backtrace (default warn, use None to disable, use Some(Level::min()) to enable all)
+	/// minimum level for backtrace (default ERROR, use None to disable, use Some(Level::min()) to enable all)
+	/// applied by HTracer::globalContext_set to the global context (all traces, not only the bridged ones) if it has no backtrace policy
+	#[deprecated(note = "use the minLevel of the context backtrace policy (Context::backtracePolicy_set)")]
 	pub min_level_backtrace: Option<Level>,
 
 	/// Htrace.level::DEBUG is the minimum level (that must be set here), correspond to: Log.level::Trace is the maximum level
@@ -44,15 +48,28 @@ pub struct HtraceBridge {
 impl HtraceBridge {
 
 	/// return if a level is a backtrace level (equal or above the "min_level_backtrace")
+	#[deprecated(note = "use BacktracePolicy::isCaptured with the context backtrace policy")]
+	#[allow(deprecated)]
 	pub fn isBacktrace(&self, level: &Level) -> bool {
-		if let Some(backtracelevel) = self.min_level_backtrace
+		return BacktracePolicy {
+			minLevel: self.min_level_backtrace,
+			..Default::default()
+		}.isCaptured(*level);
+	}
+
+	/// set the backtrace policy of the global context from the deprecated "min_level_backtrace", if it has none and the level is not the default one
+	#[allow(deprecated)]
+	pub(crate) fn backtracePolicy_apply(&self, context: &mut crate::components::context::Context) {
+		let policy = BacktracePolicy::default();
+		if(context.backtracePolicy_get().is_none() && self.min_level_backtrace != policy.minLevel)
 		{
-			return level.tou8() >= backtracelevel.tou8();
+			context.backtracePolicy_set(Some(BacktracePolicy {
+				minLevel: self.min_level_backtrace,
+				..policy
+			}));
 		}
-		return false;
 	}
 
-
 	#[cfg(feature = "log_consumer")]
 	/// return if a level is a log level (equal or above the "log_min_level")
 	pub fn isLog(&self, level: &Level) -> bool {
@@ -100,9 +117,10 @@ impl HtraceBridge {
 }
 
 impl Default for HtraceBridge {
+	#[allow(deprecated)]
 	fn default() -> Self {
 		HtraceBridge {
-			min_level_backtrace: Some(Level::WARNING),
+			min_level_backtrace: BacktracePolicy::default().minLevel,
 			#[cfg(feature = "log_consumer")]
 			log_min_level: Level::DEBUG,
 			#[cfg(feature = "tracing_consumer")]
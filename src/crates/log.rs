@@ -1,30 +1,32 @@
 use log::{Level as LogLevel, Log, Metadata, Record};
 use crate::components::level::Level;
+use crate::components::trace::TraceSource;
 use crate::crates::bridge::HtraceBridge;
 use crate::htracer;
 
 impl Log for HtraceBridge {
-	fn enabled(&self, _: &Metadata) -> bool {
-		true
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		let convertedLevel = LogLevelToHtraceMapper(&metadata.level());
+		return self.isLog(&convertedLevel) && htracer::HTracer::enabled(convertedLevel);
 	}
 
 	fn log(&self, record: &Record) {
-		let convertedLevel = LogLevelToHtraceMapper(&record.level());
-		if(!self.isLog(&convertedLevel))
+		if(!self.enabled(record.metadata()))
 		{
 			return;
 		}
+		let convertedLevel = LogLevelToHtraceMapper(&record.level());
 		let file = record.file().unwrap_or("unknown");
 		let line = record.line().unwrap_or(0);
-		let arg = format!("{}",record.args());
-
-		let mut backtrace = vec![];
-		if(self.isBacktrace(&convertedLevel))
-		{
-			backtrace = htracer::HTracer::backtrace(file);
-		}
 
-		htracer::HTracer::trace(&arg, convertedLevel, file, line,backtrace);
+		htracer::HTracer::trace_at(record.args(), convertedLevel, TraceSource {
+			file,
+			line,
+			column: 0,
+			module: record.module_path_static().unwrap_or_default(),
+			func: "",
+			template: record.args().as_str().unwrap_or_default(),
+		});
 	}
 
 	fn flush(&self) {}
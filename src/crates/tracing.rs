@@ -1,11 +1,14 @@
 use crate::components::level::Level;
 use tracing::{Level as TracingLevel};
 
+use std::cell::RefCell;
 use std::fmt;
-use tracing::{Event, Subscriber, field::{Field, Visit}};
+use std::time::Instant;
+use tracing::{Event, Subscriber, field::{Field, Visit}, span::{Attributes, Id}};
 use tracing_subscriber::{layer::{Context as TContext, Layer}, registry::LookupSpan};
 use crate::components::context::Context;
 use crate::components::span::Span;
+use crate::components::trace::TraceSource;
 use crate::crates::bridge::HtraceBridge;
 use crate::htracer::HTracer;
 
@@ -33,11 +36,104 @@ impl Visit for Visitor {
 	}
 }
 
+/// Htrace span of a tracing span (stored in its extensions), created once with the tracing span:
+/// its context (and context id) is installed each time the tracing span is entered
+#[derive(Clone)]
+struct HtraceSpan
+{
+	context: Context,
+	id: u64,
+	start: Instant,
+	source: TraceSource<'static>,
+	/// the enter event was emitted (at the first enter)
+	entered: bool,
+}
+
+thread_local! {
+	/// Htrace spans installed for the tracing spans entered by this thread (a tracing span can be entered again, or by several threads)
+	static ENTERED: RefCell<Vec<(Id, Span)>> = const { RefCell::new(vec![]) };
+}
+
+/// remove the Htrace spans of a tracing span exited by this thread (dropped outside of the borrow)
+fn entered_remove(id: &Id, all: bool)
+{
+	let removed = ENTERED.try_with(|entered| {
+		let mut entered = entered.borrow_mut();
+		let mut removed = vec![];
+		while let Some(position) = entered.iter().rposition(|(one, _)| one == id)
+		{
+			removed.push(entered.remove(position).1);
+			if(!all)
+			{
+				break;
+			}
+		}
+		removed
+	}).unwrap_or_default();
+	drop(removed);
+}
+
 impl<S> Layer<S> for HtraceBridge
 where
 	S: Subscriber + for<'a> LookupSpan<'a>,
 {
-	fn on_event(&self, event: &Event<'_>, ctx: TContext<'_, S>) {
+	fn on_new_span(&self, _: &Attributes<'_>, id: &Id, ctx: TContext<'_, S>) {
+		let Some(span) = ctx.span(id) else {return};
+		let mut context = Context::default();
+		context.name_set(span.name());
+		let metadata = span.metadata();
+		span.extensions_mut().insert(HtraceSpan {
+			context,
+			id: Span::id_next(),
+			start: Instant::now(),
+			source: TraceSource {
+				file: metadata.file().unwrap_or("<unknown>"),
+				line: metadata.line().unwrap_or(0),
+				column: 0,
+				module: metadata.module_path().unwrap_or_default(),
+				func: "",
+				template: metadata.name(),
+			},
+			entered: false,
+		});
+	}
+
+	/// the tracing span is installed as an Htrace span while entered, giving the span path of the traces
+	/// its enter event is emitted at the first enter, and its exit event when it is closed
+	fn on_enter(&self, id: &Id, ctx: TContext<'_, S>) {
+		let Some(span) = ctx.span(id) else {return};
+		let (htraceSpan, first) = {
+			let mut extensions = span.extensions_mut();
+			let Some(htraceSpan) = extensions.get_mut::<HtraceSpan>() else {return};
+			let first = !htraceSpan.entered;
+			htraceSpan.entered = true;
+			(htraceSpan.clone(), first)
+		};
+		let installed = Span::bridged(htraceSpan.context, htraceSpan.id, htraceSpan.start, htraceSpan.source, first, false);
+		let _ = ENTERED.try_with(|entered| entered.borrow_mut().push((id.clone(), installed)));
+	}
+
+	fn on_exit(&self, id: &Id, _: TContext<'_, S>) {
+		entered_remove(id, false);
+	}
+
+	fn on_close(&self, id: Id, ctx: TContext<'_, S>) {
+		entered_remove(&id, true);
+
+		// the context is installed again for its exit event
+		let Some(span) = ctx.span(&id) else {return};
+		let Some(htraceSpan) = span.extensions().get::<HtraceSpan>().filter(|one| one.entered).cloned() else {return};
+		drop(Span::bridged(htraceSpan.context, htraceSpan.id, htraceSpan.start, htraceSpan.source, false, true));
+	}
+
+	fn on_event(&self, event: &Event<'_>, _: TContext<'_, S>) {
+		// checked before visiting the fields (not in Layer::enabled, its result would be cached for every threads)
+		let convertedLevel  = TracingLevelToHtraceMapper(event.metadata().level());
+		if(!self.isTracing(&convertedLevel) || !HTracer::enabled(convertedLevel))
+		{
+			return;
+		}
+
 		let mut v = Visitor::new();
 		event.record(&mut v);
 
@@ -53,44 +149,19 @@ where
 			msg.push('}');
 		}
 
-		// get span
-		let mut span_str = None;
-		if let Some(scope) = ctx.event_scope(event) {
-			let mut tmp = String::new();
-			for (i, span) in scope.from_root().enumerate() {
-				if i > 0 { tmp.push_str("::"); }
-				tmp = format!("{}{}", tmp, span.name());
-			}
-			span_str = Some(tmp);
-		}
-
-		let convertedLevel  = TracingLevelToHtraceMapper(event.metadata().level());
-		if(!self.isTracing(&convertedLevel))
-		{
-			return;
-		}
-
 		let file = event.metadata().file().unwrap_or("<unknown>");
 		let line = event.metadata().line().unwrap_or(0);
 
-		let mut backtrace = vec![];
-		if(self.isBacktrace(&convertedLevel))
-		{
-			backtrace = HTracer::backtrace(file);
-		}
-
-		if let Some(span) = span_str
-		{
-			let mut context = Context::default();
-			context.name_set(span);
+		let source = TraceSource {
+			file,
+			line,
+			column: 0,
+			module: event.metadata().module_path().unwrap_or_default(),
+			func: "",
+			template: event.metadata().name(),
+		};
 
-			let _span = Span::new(context);
-			HTracer::trace(&msg, convertedLevel, file, line, backtrace);
-		}
-		else
-		{
-			HTracer::trace(&msg, convertedLevel, file, line, backtrace);
-		}
+		HTracer::trace_at(&msg, convertedLevel, source);
 	}
 }
 
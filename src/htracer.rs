@@ -1,17 +1,21 @@
-use std::any::Any;
-use std::fmt::{Debug, Display};
-use crate::components::trace::OneTrace;
+use crate::components::error::TraceableError;
+use crate::components::message::IntoTraceMessage;
+use crate::components::trace::{OneTrace, TraceSource};
 use crate::components::level::Level;
-use std::sync::{OnceLock};
-use time::OffsetDateTime;
-use crate::components::hbacktrace::Hbacktrace;
+use crate::components::span::SpanEvent;
+use crate::components::rate_limit::{RateLimitCheck, RateLimitSummary, RateLimiter};
+use std::cell::Cell;
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use time::{OffsetDateTime, UtcOffset};
+use crate::components::hbacktrace::{Hbacktrace, HbacktraceUnresolved};
 use crate::components::context::Context;
 use crate::context_manager::ContextManager;
 use crate::thread_manager::{ThreadManager, MAIN_THREAD};
 
 use std::{mem, thread};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use singletonThread::SingletonThread;
 use parking_lot::RwLock;
 #[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
@@ -20,14 +24,22 @@ use crate::crates::bridge::HtraceBridge;
 pub struct HTracer
 {
 	_deferredTraces: RwLock<Vec<OneTrace>>,
-	_threadWriting: RwLock<SingletonThread>
+	_threadWriting: RwLock<SingletonThread>,
+	_rateLimiter: RateLimiter,
 }
 
 static CONTEXTSET: OnceLock<RwLock<bool>> = OnceLock::new();
 static SINGLETON: OnceLock<HTracer> = OnceLock::new();
+static STARTED: OnceLock<Instant> = OnceLock::new();
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
 
 impl HTracer
 {
+	thread_local!{
+		/// instant of the last trace of this thread (for OneTrace.delta)
+		static LASTTRACE: Cell<Option<Instant>> = const { Cell::new(None) };
+	}
+
 	pub fn singleton() -> &'static HTracer
 	{
 		if(*CONTEXTSET.get_or_init(|| RwLock::new(false)).read() == false) {
@@ -48,11 +60,14 @@ impl HTracer
 	                         bridge: HtraceBridge)
 	{
 		let contextSet = CONTEXTSET.get_or_init(|| RwLock::new(false));
+		STARTED.get_or_init(Instant::now);
 
 		if(context.threadName_get().is_none())
 		{
 			context.threadName_set(MAIN_THREAD);
 		}
+		#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+		bridge.backtracePolicy_apply(&mut context);
 
 		// auto define of the bridge is only done one time
 		if(*contextSet.read() == false)
@@ -93,119 +108,121 @@ impl HTracer
 
 	}
 	
-	pub fn trace<T>(rawEntry : &T, level: Level, file: &str, line: u32, backtraces: Vec<Hbacktrace>)
-		where T: Any + Debug // + ?Display
+	/// true if a trace of this level would be emitted in the current thread (its level is above the effective minimum level)
+	/// cheap, used by the macros to skip formatting of disabled traces
+	pub fn enabled(level: Level) -> bool
 	{
-		let anyEntry = rawEntry as &dyn Any;
-		let tmp = if let Some(content) = anyEntry.downcast_ref::<String>() {
-			content.to_string()
-		}
-		else
-		{
-			if let Some(content) = anyEntry.downcast_ref::<&str>()
-			{
-				content.to_string()
-			}
-			else
-			{
-				if let Some(content)= anyEntry.downcast_ref::<Box<dyn Display>>()
-				{
-					format!("{}", content)
-				}
-				else
-				{
-					format!("{:?}", rawEntry)
-				}
-			}
-		};
+		return level.tou8() >= ContextManager::singleton().level_getMin().tou8();
+	}
 
-		let context = ContextManager::singleton().resolve();
-		if(level.tou8() < context.level_getMin().unwrap_or(&Level::DEBUG).tou8()) {
+	/// emit a trace (the HTrace! macro is the usual way to call it)
+	/// the message can be anything implementing Display (see IntoTraceMessage)
+	pub fn trace<T>(message: &T, level: Level, file: &str, line: u32, backtraces: Vec<Hbacktrace>)
+		where T: IntoTraceMessage + ?Sized
+	{
+		let Some(mut trace) = Self::trace_new(level, TraceSource {
+			file,
+			line,
+			..Default::default()
+		}) else {
 			return;
-		}
-
-		let trace = OneTrace {
-			message: tmp.clone(),
-			date: OffsetDateTime::now_utc(),
-			level,
-			context,
-			filename: file.to_string(),
-			fileline: line,
-			backtraces,
 		};
 
-		thread::spawn(move ||{
-			Self::singleton()._deferredTraces.write().push(trace);
-			Self::singleton()._threadWriting.write().thread_launch_delayabe();
-		});
+		trace.message = message.message_get();
+		trace.backtraces = backtraces;
+		Self::trace_push(trace);
 	}
-	
-	pub fn backtrace(base: &str) -> Vec<Hbacktrace>
-	{
-		let mut internal = true;
-		let mut returning = Vec::new();
-
-		// in case of no filename/path present, we use the current symbol name
-		let bt = backtrace::Backtrace::new();
-		let thisBTName = bt.frames()[0].symbols()[0].name().map(|x| x.to_string()).unwrap_or_default();
-		//println!("{:?}",bt.frames()[0].symbols()[0].name());
-
-		//println!("frame : -----");
-		backtrace::trace(|x|{
-			backtrace::resolve_frame(x, |symbol| {
-				//println!("frame symbol : {:?} => {:?} => {:?} => {:?}",symbol.name(),symbol.filename(), symbol.lineno(),base);
-
-				let name = symbol.name().map(|sbname| sbname.to_string());
-				let filename = symbol.filename().map(|x| x.to_str().unwrap_or_default().to_string());
-				let line = symbol.lineno();
 
-				// solvable
-				let Some(name) = name else {return;};
-				let mut normalizedName = name.clone();
+	/// same as trace(), with the full source location (see HTraceSource!())
+	/// the backtrace is captured according to the backtrace policy (see backtrace_forLevel()), only if the trace is kept by the rate limit and the sampling
+	pub fn trace_at<T>(message: &T, level: Level, source: TraceSource)
+		where T: IntoTraceMessage + ?Sized
+	{
+		let Some(mut trace) = Self::trace_new(level, source) else {
+			return;
+		};
 
-				if let Some((splitted,_)) = name.rsplit_once("::")
-				{
-					normalizedName = format!("{}()",splitted);
-				}
+		trace.message = message.message_get();
+		trace.backtraceUnresolved = Self::backtrace_forLevel(level);
+		Self::trace_push(trace);
+	}
 
+	/// trace an error, its causes (Error::source() chain) being stored in OneTrace.causes (see HTraceError! and ResultExt)
+	/// the backtrace captured by the error is used if any (anyhow), else one is captured according to the backtrace policy
+	pub fn trace_error<T, E, M>(message: &T, error: &E, level: Level, source: TraceSource)
+		where T: IntoTraceMessage + ?Sized,
+		      E: TraceableError<M> + ?Sized
+	{
+		let Some(mut trace) = Self::trace_new(level, source) else {
+			return;
+		};
 
-				match &filename
-				{
-					None => {
-						if(name == thisBTName)
-						{
-							internal = false;
-							return; // start on the next symbol
-						}
-					}
-					Some(filename) => {
-						if(filename.ends_with(base))
-						{
-							internal = false;
-						}
-
-						// ignore rustc backtrace part
-						if(filename.starts_with("/rustc/")) {
-							return;
-						}
-					}
-				}
+		trace.message = message.message_get();
+		match error.error_backtrace() {
+			Some(backtraces) => trace.backtraces = backtraces,
+			None => trace.backtraceUnresolved = Self::backtrace_forLevel(level),
+		}
+		trace.causes = error.error_causes();
+		Self::trace_push(trace);
+	}
+	
+	/// unresolved backtrace of the caller, if the backtrace policy of the current context captures this level (see BacktracePolicy)
+	/// its symbols are resolved by the writer thread
+	pub fn backtrace_forLevel(level: Level) -> Option<HbacktraceUnresolved>
+	{
+		let context = ContextManager::singleton().resolve();
+		let policy = context.backtracePolicy_get().cloned().unwrap_or_default();
+		if(!policy.isCaptured(level))
+		{
+			return None;
+		}
+		return Some(HbacktraceUnresolved::capture(policy));
+	}
 
+	/// resolved backtrace of the caller, frames are kept according to the backtrace policy of the current context (see BacktracePolicy)
+	pub fn backtrace() -> Vec<Hbacktrace>
+	{
+		let context = ContextManager::singleton().resolve();
+		let policy = context.backtracePolicy_get().cloned().unwrap_or_default();
+		return HbacktraceUnresolved::capture(policy).resolve();
+	}
+	
+	/// notify the modules that the span of the last context of the stack is entered or exited,
+	/// with its enter/exit trace if enabled (see Context::spanTraces_set)
+	pub(crate) fn span_event(event: SpanEvent, duration: Duration, source: TraceSource<'static>)
+	{
+		if(*CONTEXTSET.get_or_init(|| RwLock::new(false)).read() == false) {
+			return;
+		}
 
-				// probably the /rustc/ part is not multiplatform safe
-				if !internal
-				{
-					returning.push(Hbacktrace {
-						funcName: normalizedName,
-						fileName: filename,
-						line,
-					});
-				}
-			});
-			true
+		let context = ContextManager::singleton().resolve();
+		let level = context.spanTraces_get().copied();
+		let mut notification = (!context.modules_get().is_empty()).then(|| {
+			let mut trace = Self::trace_create(context.clone(), level.unwrap_or(Level::NORMAL), source);
+			trace.spanEvent = Some(event);
+			trace.spanDuration = Some(duration);
+			trace
 		});
-		
-		return returning;
+		let name = context.name_get().clone().unwrap_or("span".to_string());
+
+		// modules are notified before the enter trace, and after the exit one
+		if event == SpanEvent::Enter && let Some(trace) = notification.take()
+		{
+			Self::trace_push(trace);
+		}
+		if let Some(level) = level && let Some(mut trace) = Self::trace_new(level, source)
+		{
+			trace.message = match event {
+				SpanEvent::Enter => format!("enter {}", name),
+				SpanEvent::Exit => format!("exit {} after {:?}", name, duration),
+			};
+			trace.spanDuration = Some(duration);
+			Self::trace_push(trace);
+		}
+		if let Some(trace) = notification
+		{
+			Self::trace_push(trace);
+		}
 	}
 
 	pub fn drop()
@@ -222,10 +239,131 @@ impl HTracer
 		{
 			Self::singleton().internal_writeTraces();
 		}
+
+		// summaries of the rate limited call sites, even if their window is not over
+		for summary in Self::singleton()._rateLimiter.expired(true) {
+			Self::summary_trace(summary).emit();
+		}
+
+		if let Some(global) = ContextManager::singleton().global_get()
+		{
+			global.modules_get().values().flatten().for_each(|module| {
+				module.event_onContextExit(&global);
+				module.event_onGlobalExit();
+			});
+		}
 	}
 	
 	//////////// PRIVATE ///////////
 
+	/// the global context is set (traces emitted before globalContext_set() have nowhere to go), and the level is enabled
+	fn isTraced(level: Level) -> bool
+	{
+		if(*CONTEXTSET.get_or_init(|| RwLock::new(false)).read() == false) {
+			return false;
+		}
+		return Self::enabled(level);
+	}
+
+	/// false if the trace is suppressed by the rate limit of its context (see RateLimit)
+	/// the summary of the previous window of the call site is queued before the trace
+	fn rateLimit_check(context: &Arc<Context>, level: Level, source: &TraceSource) -> bool
+	{
+		let Some(limit) = context.rateLimit_get() else {
+			return true;
+		};
+
+		return match Self::singleton()._rateLimiter.check(limit, level, source, context) {
+			RateLimitCheck::Emit => true,
+			RateLimitCheck::Suppress => false,
+			RateLimitCheck::EmitAfter(summary) => {
+				Self::singleton()._deferredTraces.write().push(Self::summary_trace(summary));
+				true
+			}
+		};
+	}
+
+	/// trace of the suppressed traces of a rate limited call site
+	fn summary_trace(summary: RateLimitSummary) -> OneTrace
+	{
+		let now = Instant::now();
+		return OneTrace {
+			message: summary.message_get(),
+			date: OffsetDateTime::now_utc().to_offset(*summary.context.timeOffset_get().unwrap_or(&UtcOffset::UTC)),
+			level: summary.level,
+			filename: summary.filename,
+			fileline: summary.fileline,
+			fileColumn: summary.fileColumn,
+			modulePath: summary.modulePath,
+			funcName: summary.funcName,
+			sequence: SEQUENCE.fetch_add(1, Ordering::Relaxed),
+			uptime: now.duration_since(*STARTED.get_or_init(Instant::now)),
+			context: summary.context,
+			..Default::default()
+		};
+	}
+
+	/// the trace of this level and source (without its message), if it's emitted :
+	/// the global context is set, the level is enabled, and the trace is kept by the sampling and the rate limit of the context
+	fn trace_new(level: Level, source: TraceSource) -> Option<OneTrace>
+	{
+		if(!Self::isTraced(level)) {
+			return None;
+		}
+
+		let context = ContextManager::singleton().resolve();
+		let sampleRate = match context.sampling_get() {
+			None => 1.0,
+			Some(sampling) => sampling.sample(level, source.file, context.name_get().as_deref())?,
+		};
+		if(!Self::rateLimit_check(&context, level, &source)) {
+			return None;
+		}
+
+		let mut trace = Self::trace_create(context, level, source);
+		let now = Instant::now();
+		trace.delta = Self::LASTTRACE.try_with(|last| last.replace(Some(now))).ok().flatten().map(|last| now.duration_since(last));
+		trace.sampleRate = sampleRate;
+		return Some(trace);
+	}
+
+	/// the trace of this level and source in the context (without its message)
+	fn trace_create(context: Arc<Context>, level: Level, source: TraceSource) -> OneTrace
+	{
+		let date = OffsetDateTime::now_utc().to_offset(*context.timeOffset_get().unwrap_or(&UtcOffset::UTC));
+		let now = Instant::now();
+		return OneTrace {
+			message: String::new(),
+			date,
+			level,
+			filename: source.file.to_string(),
+			fileline: source.line,
+			fileColumn: source.column,
+			modulePath: source.module,
+			funcName: source.func,
+			backtraces: vec![],
+			backtraceUnresolved: None,
+			causes: vec![],
+			sequence: SEQUENCE.fetch_add(1, Ordering::Relaxed),
+			uptime: now.duration_since(*STARTED.get_or_init(Instant::now)),
+			delta: None,
+			sampleRate: 1.0,
+			spanDuration: context.spanStart_get().map(|start| now.duration_since(*start)),
+			spanEvent: None,
+			context,
+		};
+	}
+
+	/// queue the trace for the writing thread
+	fn trace_push(trace: OneTrace)
+	{
+		// queued here (not in the spawned thread) to keep the creation order of this thread traces
+		Self::singleton()._deferredTraces.write().push(trace);
+		thread::spawn(move ||{
+			Self::singleton()._threadWriting.write().thread_launch_delayabe();
+		});
+	}
+
 	fn new() -> HTracer {
 		let thread = SingletonThread::new(||{
 			Self::singleton().internal_writeTraces();
@@ -234,6 +372,7 @@ impl HTracer
 		return HTracer {
 			_deferredTraces: RwLock::new(vec![]),
 			_threadWriting: RwLock::new(thread),
+			_rateLimiter: RateLimiter::new(),
 		};
 	}
 
@@ -244,10 +383,16 @@ impl HTracer
 			mem::replace(&mut *binding,vec![])
 		};
 
-		getWritingStuff.sort_by(|a,b| a.date.cmp(&b.date));
+		getWritingStuff.sort_by(|a,b| a.date.cmp(&b.date).then(a.sequence.cmp(&b.sequence)));
 		
-		for x in getWritingStuff {
+		for mut x in getWritingStuff {
+			x.backtrace_resolve();
 			x.emit();
 		}
+
+		// summaries of the rate limited call sites whose window is over
+		for summary in self._rateLimiter.expired(false) {
+			Self::summary_trace(summary).emit();
+		}
 	}
 }
\ No newline at end of file
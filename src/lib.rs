@@ -8,6 +8,7 @@ extern crate alloc;
 pub mod htracer;
 pub mod modules;
 pub mod components;
+pub mod thread;
 mod thread_manager;
 mod context_manager;
 
@@ -22,6 +23,8 @@ pub enum Errors
 	CannotFoundConfigNode(String),
 	#[error("module '{0}' configuration returned a error : {1}")]
 	ModuleConfigError(String,#[source] anyhow::Error),
+	#[error("formater '{0}' is invalid at position {1} : {2}")]
+	FormaterCompileError(String, usize, String),
 }
 
 /// Shortcut for the trace function (defaults to Type::NORMAL)
@@ -38,43 +41,128 @@ pub enum Errors
 ///
 /// ```
 ///
-/// Note: Currently, the data needs to be a string, a &str, or something that implements "Display" or "Debug".
+/// The data can be anything implementing "Display" or "Debug" (errors are rendered with their causes, see components::message).
+/// Arguments are only evaluated (and formatted) if the level is enabled in the current thread (see HTracer::enabled).
 #[macro_export]
 macro_rules! HTrace
 {
     ($a:expr) => {
-	    $crate::htracer::HTracer::trace(&$a, $crate::components::level::Level::NORMAL, file!(), line!(), vec![]);
+	    $crate::HTrace!(($crate::components::level::Level::NORMAL) $a)
     };
 	(($b:expr) $a:expr) => {
-		if($b.tou8() >= $crate::components::level::Level::ERROR.tou8())
+		if($crate::htracer::HTracer::enabled($b))
 		{
-	        $crate::htracer::HTracer::trace(&$a, $b, file!(), line!(),$crate::htracer::HTracer::backtrace(file!()));
-		}
-		else
-		{
-	        $crate::htracer::HTracer::trace(&$a, $b, file!(), line!(), vec![]);
+			use $crate::components::message::{MessageSelectError as _, MessageSelectDisplay as _, MessageSelectDebug as _};
+			$crate::htracer::HTracer::trace_at(&(&&&$crate::components::message::MessageSelect(&$a)).message_select(), $b, $crate::HTraceSource!());
 		}
     };
 	($a:expr $(,$arg:expr)*) => {
-	    $crate::htracer::HTracer::trace(&format!($a,$($arg),*), $crate::components::level::Level::NORMAL, file!(), line!(), vec![])
+	    $crate::HTrace!(($crate::components::level::Level::NORMAL) $a $(,$arg)*)
     };
 	(($b:expr) $a:expr $(,$arg:expr)*) => {
-		if($b.tou8() >= $crate::components::level::Level::ERROR.tou8())
+		if($crate::htracer::HTracer::enabled($b))
 		{
-	        $crate::htracer::HTracer::trace(&format!($a,$($arg),*), $b, file!(), line!(),$crate::htracer::HTracer::backtrace(file!()))
-		}
-		else
-		{
-	        $crate::htracer::HTracer::trace(&format!($a,$($arg),*), $b, file!(), line!(), vec![])
+			$crate::htracer::HTracer::trace_at(&format_args!($a,$($arg),*), $b, $crate::HTraceSource!($a));
 		}
     };
 }
 
+/// Shortcut for HTrace!((Level::DEBUG) ...)
+/// Arguments are not evaluated if the DEBUG level is disabled (see HTracer::enabled)
+/// ```
+/// use Htrace::HDebug;
+///
+/// let myvar = 42;
+/// HDebug!(myvar);
+/// HDebug!("this is : {}",myvar);
+/// ```
+#[macro_export]
+macro_rules! HDebug
+{
+	($($arg:tt)+) => {
+		$crate::HTrace!(($crate::components::level::Level::DEBUG) $($arg)+)
+	};
+}
+
+/// Shortcut for HTrace!((Level::NOTICE) ...), see HDebug!
+#[macro_export]
+macro_rules! HNotice
+{
+	($($arg:tt)+) => {
+		$crate::HTrace!(($crate::components::level::Level::NOTICE) $($arg)+)
+	};
+}
+
+/// Shortcut for HTrace!((Level::WARNING) ...), see HDebug!
+#[macro_export]
+macro_rules! HWarn
+{
+	($($arg:tt)+) => {
+		$crate::HTrace!(($crate::components::level::Level::WARNING) $($arg)+)
+	};
+}
+
+/// Shortcut for HTrace!((Level::ERROR) ...), with backtrace by default (see BacktracePolicy), see HDebug!
+#[macro_export]
+macro_rules! HError
+{
+	($($arg:tt)+) => {
+		$crate::HTrace!(($crate::components::level::Level::ERROR) $($arg)+)
+	};
+}
+
+/// Shortcut for HTrace!((Level::FATAL) ...), with backtrace by default (see BacktracePolicy), see HDebug!
+#[macro_export]
+macro_rules! HFatal
+{
+	($($arg:tt)+) => {
+		$crate::HTrace!(($crate::components::level::Level::FATAL) $($arg)+)
+	};
+}
+
+/// Source location of the macro call (file, line, column, module path and enclosing function), see TraceSource
+/// the format string of the trace can be given as template
+/// ```
+/// use Htrace::HTraceSource;
+///
+/// fn connect()
+/// {
+///     let source = HTraceSource!();
+///     assert_eq!(source.func, "connect");
+/// }
+///
+/// fn main()
+/// {
+///     connect();
+/// }
+/// ```
+#[macro_export]
+macro_rules! HTraceSource
+{
+	() => {
+		$crate::HTraceSource!("")
+	};
+	($template:expr) => {{
+		// the type name of a function item defined here contains the enclosing function path
+		fn htrace_source_marker() {}
+		fn htrace_type_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() }
+		$crate::components::trace::TraceSource {
+			file: file!(),
+			line: line!(),
+			column: column!(),
+			module: module_path!(),
+			func: $crate::components::trace::TraceSource::functionName(htrace_type_name(htrace_source_marker), module_path!()),
+			template: $template,
+		}
+	}};
+}
+
 /// Shortcut for the trace function for Result>Error (defaults to Type::ERROR)
-/// Takes a result, and if it is in error, traces it.
+/// Takes a result, and if it is in error, traces it with its causes (see HTracer::trace_error).
 /// Does nothing if the result is OK.
 /// This only makes sense if you want to receive the error information for debugging.
-/// Works like HTrace!()
+/// Works like HTrace!(), the error can be a std error, a boxed error, an anyhow::Error or a string (see components::error::TraceableError)
+/// The error is rendered with its causes ("error : cause : root cause"), use ResultExt to trace a result and keep it.
 /// Can be used like this:
 /// ```
 /// use Htrace::HTraceError;
@@ -87,23 +175,19 @@ macro_rules! HTrace
 macro_rules! HTraceError
 {
 	($a:expr) => {
-		if let Err(errorToTrace) = $a {
-			$crate::HTrace!(($crate::components::level::Level::ERROR) errorToTrace);
-		}
+		$crate::HTraceError!(($crate::components::level::Level::ERROR) $a)
     };
 	($desc:expr,$a:expr) => {
-		if let Err(errorToTrace) = $a {
-			$crate::HTrace!(($crate::components::level::Level::ERROR) $desc,errorToTrace);
-		}
+		$crate::HTraceError!(($crate::components::level::Level::ERROR) $desc,$a)
     };
 	(($b:expr) $a:expr) => {
-		if let Err(errorToTrace) = $a {
-			$crate::HTrace!(($b) errorToTrace);
+		if let Err(errorToTrace) = $a && $crate::htracer::HTracer::enabled($b) {
+			$crate::htracer::HTracer::trace_error(&$crate::components::error::TraceableError::error_chain(&errorToTrace), &errorToTrace, $b, $crate::HTraceSource!());
 		}
     };
 	(($b:expr) $desc:expr,$a:expr) => {
-		if let Err(errorToTrace) = $a {
-			$crate::HTrace!(($b) $desc, errorToTrace);
+		if let Err(errorToTrace) = $a && $crate::htracer::HTracer::enabled($b) {
+			$crate::htracer::HTracer::trace_error(&format_args!($desc, $crate::components::error::TraceableError::error_chain(&errorToTrace)), &errorToTrace, $b, $crate::HTraceSource!($desc));
 		}
     };
 }
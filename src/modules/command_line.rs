@@ -1,40 +1,54 @@
 use crate::modules::module_abstract::ModuleAbstract;
 use crate::components::trace::OneTrace;
 use anyhow::Result;
-use owo_colors::{OwoColorize, Style};
+use parking_lot::Mutex;
+use crate::Errors;
+use owo_colors::Style;
 use crate::components::context::Context;
-use crate::components::formater::FormaterCompiled;
+use crate::components::formater::{Formatter, TemplateFormatter};
 use crate::modules::command_line_config::CommandLineConfig;
 
 pub struct CommandLine
 {
 	_name: String,
 	_configs: CommandLineConfig,
-	_formaterCompiled: FormaterCompiled,
+	_formatter: Box<dyn Formatter>,
+	/// line of the current trace, reused for each trace
+	_line: Mutex<String>,
 }
 
 impl CommandLine
 {
+	/// create the module
+	/// panic if the lineFormat cannot be compiled, see try_new()
 	pub fn new(config: CommandLineConfig) -> CommandLine {
-		let binding = &config.formaterCompiler;
-		let fmtComp = binding(&config.lineFormat);
-		return CommandLine{
+		return match Self::try_new(config) {
+			Ok(module) => module,
+			Err(err) => panic!("[Htrace] {}", err),
+		};
+	}
+
+	/// create the module, return an error if the lineFormat cannot be compiled
+	pub fn try_new(mut config: CommandLineConfig) -> Result<CommandLine, Errors> {
+		let formatter = match config.formatter.take() {
+			Some(formatter) => formatter,
+			None => Box::new(TemplateFormatter::with_options(&config.lineFormat, config.formaterOptions())?),
+		};
+		return Ok(CommandLine{
 			_name: String::new(),
 			_configs: config,
-			_formaterCompiled: fmtComp,
-		};
+			_formatter: formatter,
+			_line: Mutex::new(String::new()),
+		});
 	}
 	
 	pub fn draw(&self,trace: &OneTrace)
 	{
-		let color = self._configs.colors.get(&trace.level).unwrap_or(&Style::new()).clone();
-
-		let binding = &self._configs.formaterParamBuilder;
-		let mut parameters = binding(trace, &self._configs.lineReturn);
-		parameters.get_mut("lvl").iter_mut().for_each(|x| **x = x.style(color).to_string());
-		parameters.get_mut("msg").iter_mut().for_each(|x| **x = x.style(color).to_string());
-
-		println!("{}",self._formaterCompiled.render(parameters));
+		let color = self._configs.colors.get(&trace.level).copied().unwrap_or(Style::new());
+		let mut line = self._line.lock();
+		line.clear();
+		self._formatter.format_into(trace, Some(&color), &mut line);
+		println!("{}", line);
 	}
 }
 
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use owo_colors::Style;
-use crate::components::formater::{FormaterCompile, FormaterCompilerSignature, FormaterParamBuilder, FormaterParamBuilderSignature};
+use crate::components::formater::{FormaterMultiline, FormaterOptions, Formatter};
 use crate::components::level::Level;
 
 #[cfg(feature = "hconfig")]
@@ -12,12 +12,14 @@ pub struct CommandLineConfig
 	pub colors: HashMap<Level,Style>,
 	/// adding string when a trace have à return char "\n"/"\r"
 	pub lineReturn: String,
-	/// format of the trace, view HtraceDefaultFormater for available variable
+	/// format of the trace, view FormaterCompile for the syntax and available variables
 	pub lineFormat: String,
-	/// define the way to collect data (using lineReturn)
-	pub formaterParamBuilder: FormaterParamBuilderSignature,
-	/// define the way convert collected data into string (using lineFormat)
-	pub formaterCompiler: FormaterCompilerSignature
+	/// rendering of multi-line messages : indented with lineReturn (default), escaped as "\\n", or one line per message line
+	pub multiline: FormaterMultiline,
+	/// also consider escaped "\\n" and "\\r" in messages as line returns (default true)
+	pub interpretEscapes: bool,
+	/// custom formatter, replacing the TemplateFormatter built from lineFormat and lineReturn
+	pub formatter: Option<Box<dyn Formatter>>,
 }
 
 impl Default for CommandLineConfig
@@ -37,8 +39,22 @@ impl Default for CommandLineConfig
 			colors,
 			lineReturn: " | ".to_string(),
 			lineFormat: "{time} {lvl} ({thread:>, }{context:>, }{file}:l{line}) : {msg}".to_string(),
-			formaterParamBuilder: FormaterParamBuilder,
-			formaterCompiler: FormaterCompile,
+			multiline: FormaterMultiline::Indent,
+			interpretEscapes: true,
+			formatter: None,
+		};
+	}
+}
+
+impl CommandLineConfig
+{
+	/// options of the default formatter
+	pub fn formaterOptions(&self) -> FormaterOptions
+	{
+		return FormaterOptions {
+			lineReturn: self.lineReturn.clone(),
+			multiline: self.multiline,
+			interpretEscapes: self.interpretEscapes,
 		};
 	}
 }
@@ -48,7 +64,8 @@ impl CommandLineConfig
 {
 	pub fn create_from_hconfig(configs: &mut JsonValue, mut newConfig: Self) -> Self
 	{
-		use crate::modules::utils_hconfig::setConfig_String;
+		use crate::components::formater::FormaterCompile;
+		use crate::modules::utils_hconfig::{setConfig_String, setConfig_boolean};
 
 		let JsonValue::Object(config) = configs else { return newConfig };
 
@@ -93,8 +110,12 @@ impl CommandLineConfig
 
 		setConfig_String(config,"lineReturn",&mut newConfig.lineReturn,|_|true);
 		setConfig_String(config,"lineFormat",&mut newConfig.lineFormat,|a|{
-			!a.contains("{color}")
+			!a.contains("{color}") && FormaterCompile(&a.to_string()).is_ok()
 		});
+		let mut multiline = newConfig.multiline.name().to_string();
+		setConfig_String(config,"multiline",&mut multiline, |a| FormaterMultiline::fromName(a).is_some());
+		newConfig.multiline = FormaterMultiline::fromName(&multiline).unwrap_or(newConfig.multiline);
+		setConfig_boolean(config,"interpretEscapes",&mut newConfig.interpretEscapes);
 
 		newConfig
 	}
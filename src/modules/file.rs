@@ -2,39 +2,56 @@ use std::fs::{create_dir_all, OpenOptions};
 use std::io::Write;
 use std::path::Path;
 use anyhow::Result;
+use parking_lot::Mutex;
+use crate::Errors;
 use time::macros::format_description;
 use crate::components::context::Context;
-use crate::components::formater::FormaterCompiled;
+use crate::components::formater::{Formatter, TemplateFormatter};
 use crate::modules::module_abstract::ModuleAbstract;
 use crate::components::trace::OneTrace;
 use crate::modules::file_config::FileConfig;
-use crate::thread_manager::{ThreadManager, MAIN_THREAD};
+use crate::thread_manager::MAIN_THREAD;
 
 pub struct File
 {
 	_name: String,
 	_configs: FileConfig,
-	_formaterCompiled: FormaterCompiled,
+	_formatter: Box<dyn Formatter>,
+	/// line of the current trace, reused for each trace
+	_line: Mutex<String>,
 }
 
 impl File
 {
+	/// create the module
+	/// panic if the lineFormat cannot be compiled, see try_new()
 	pub fn new(config: FileConfig) -> File {
-		let binding = &config.formaterCompiler;
-		let fmtComp = binding(&config.lineFormat);
-		return File{
+		return match Self::try_new(config) {
+			Ok(module) => module,
+			Err(err) => panic!("[Htrace] {}", err),
+		};
+	}
+
+	/// create the module, return an error if the lineFormat cannot be compiled
+	pub fn try_new(mut config: FileConfig) -> Result<File, Errors> {
+		let formatter = match config.formatter.take() {
+			Some(formatter) => formatter,
+			None => Box::new(TemplateFormatter::with_options(&config.lineFormat, config.formaterOptions())?),
+		};
+		return Ok(File{
 			_name: String::new(),
 			_configs: config,
-			_formaterCompiled: fmtComp,
-		};
+			_formatter: formatter,
+			_line: Mutex::new(String::new()),
+		});
 	}
 	
 	fn generateLine(&self, trace: &OneTrace)
 	{
-
-		let binding = &self._configs.formaterParamBuilder;
-		let parameters = binding(trace, &self._configs.lineReturn);
-		let formatResult = self._formaterCompiled.render(parameters);
+		let mut line = self._line.lock();
+		line.clear();
+		self._formatter.format_into(trace, None, &mut line);
+		line.push('\n');
 
 		let mut filedateformat = format_description!("[year][month][day]");
 		if(self._configs.byHour)
@@ -45,26 +62,44 @@ impl File
 		if self._configs.forceInOneFile.is_some()
 		{
 			let path = format!("{}/{}_{}.trc",self._configs.path,self._configs.forceInOneFile.clone().unwrap(),trace.date.format(filedateformat).unwrap_or("00000000".to_string()));
-			self.writeToFile(path, formatResult.clone());
+			self.writeToFile(path, &line);
 		};
 		if self._configs.bySrc
 		{
-			let filename = trace.filename.clone();
-			let tmp: Vec<_> = filename.split("/").collect();
-			let tmp: Vec<_> = tmp.last().unwrap().split(".").collect();
-			let filename = tmp[0].to_string();
+			// the module path is unique, the file name is only used when the module is unknown (ex: traces from bridges without module)
+			let filename = if(!trace.modulePath.is_empty()) {
+				trace.modulePath.replace("::", ".")
+			}
+			else
+			{
+				let tmp: Vec<_> = trace.filename.split("/").collect();
+				let tmp: Vec<_> = tmp.last().unwrap().split(".").collect();
+				tmp[0].to_string()
+			};
 			let path = format!("{}/{}_{}.trc",self._configs.path,filename,trace.date.format(filedateformat).unwrap_or("00000000".to_string()));
-			self.writeToFile(path, formatResult.clone());
+			self.writeToFile(path, &line);
 		}
 		if self._configs.byThreadId
 		{
-			let filename = ThreadManager::local_getName().unwrap_or(MAIN_THREAD.to_string());
+			let filename = trace.context.threadName_get().as_deref().map(Self::threadFileName).unwrap_or(MAIN_THREAD.to_string());
 			let path = format!("{}/{}_{}.trc",self._configs.path,filename,trace.date.format(filedateformat).unwrap_or("00000000".to_string()));
-			self.writeToFile(path, formatResult.clone());
+			self.writeToFile(path, &line);
+		}
+	}
+	
+	/// file name of a thread: unnamed threads (displayed "ThreadId(5)") become "thread5", chars unsafe in a file name are replaced by "_"
+	fn threadFileName(threadName: &str) -> String
+	{
+		if let Some(id) = threadName.strip_prefix("ThreadId(").and_then(|id| id.strip_suffix(")"))
+		{
+			return format!("thread{}", id);
 		}
+		return threadName.chars().map(|c| {
+			if(c.is_alphanumeric() || c == '_' || c == '-' || c == '.') {c} else {'_'}
+		}).collect();
 	}
 	
-	fn writeToFile(&self, filepath: String,lineToWrite: String)
+	fn writeToFile(&self, filepath: String,lineToWrite: &str)
 	{
 		let filepathC = Path::new(filepath.as_str());
 		if(filepathC.parent().is_none())
@@ -83,7 +118,7 @@ impl File
 		}
 		let mut Rfile = Rfile.unwrap();
 		
-		let _iswrited = Rfile.write(format!("{}\n",lineToWrite).as_bytes());
+		let _iswrited = Rfile.write_all(lineToWrite.as_bytes());
 	}
 	
 }
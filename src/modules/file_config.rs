@@ -1,4 +1,4 @@
-use crate::components::formater::{FormaterCompile, FormaterCompilerSignature, FormaterParamBuilder, FormaterParamBuilderSignature};
+use crate::components::formater::{FormaterMultiline, FormaterOptions, Formatter};
 
 #[cfg(feature = "hconfig")]
 use Hconfig::tinyjson::JsonValue;
@@ -10,20 +10,23 @@ pub struct FileConfig
 	pub path: String,
 	/// adding string when a trace have à return char "\n"/"\r"
 	pub lineReturn: String,
-	/// format of the trace, view HtraceDefaultFormater for available variable
+	/// format of the trace, view FormaterCompile for the syntax and available variables
 	pub lineFormat: String,
+	/// rendering of multi-line messages : indented with lineReturn (default), escaped as "\\n", or one line per message line
+	pub multiline: FormaterMultiline,
+	/// also consider escaped "\\n" and "\\r" in messages as line returns (default true)
+	pub interpretEscapes: bool,
 	/// write a file by thread name
 	pub byThreadId: bool,
-	/// write a file by src of trace
+	/// write a file by src of trace (named by module path, like "mycrate.network_20240131.trc", or by file name if the module is unknown)
 	pub bySrc: bool,
 	/// each file wrote is by hour (add hour after date in filename)
+	/// note: the date of file names use the context offset (see Context::timeOffset_set)
 	pub byHour: bool,
 	/// write all trace in one file (auto append "_{time}.trc")
 	pub forceInOneFile: Option<String>,
-	/// define the way to collect data (using lineReturn)
-	pub formaterParamBuilder: FormaterParamBuilderSignature,
-	/// define the way convert collected data into string (using lineFormat)
-	pub formaterCompiler: FormaterCompilerSignature
+	/// custom formatter, replacing the TemplateFormatter built from lineFormat and lineReturn
+	pub formatter: Option<Box<dyn Formatter>>,
 }
 
 impl Default for FileConfig
@@ -33,12 +36,26 @@ impl Default for FileConfig
 			path: "./traces".to_string(),
 			lineReturn: " | ".to_string(),
 			lineFormat: "{time} {lvl} ({thread:>, }{context:>, }{file}:l{line}) : {msg}".to_string(),
+			multiline: FormaterMultiline::Indent,
+			interpretEscapes: true,
 			byThreadId: true,
 			bySrc: false,
 			byHour: false,
 			forceInOneFile: None,
-			formaterParamBuilder: FormaterParamBuilder,
-			formaterCompiler: FormaterCompile,
+			formatter: None,
+		};
+	}
+}
+
+impl FileConfig
+{
+	/// options of the default formatter
+	pub fn formaterOptions(&self) -> FormaterOptions
+	{
+		return FormaterOptions {
+			lineReturn: self.lineReturn.clone(),
+			multiline: self.multiline,
+			interpretEscapes: self.interpretEscapes,
 		};
 	}
 }
@@ -48,13 +65,18 @@ impl FileConfig
 {
 	pub fn create_from_hconfig(configs: &mut JsonValue, mut newConfig: Self) -> Self
 	{
+		use crate::components::formater::FormaterCompile;
 		use crate::modules::utils_hconfig::{setConfig_String, setConfig_boolean};
 		use Hconfig::tinyjson::JsonValue;
 
 		let JsonValue::Object(config) = configs else {return newConfig};
 		setConfig_String(config,"path",&mut newConfig.path, |_|true);
 		setConfig_String(config,"lineReturn",&mut newConfig.lineReturn, |_|true);
-		setConfig_String(config,"lineFormat",&mut newConfig.lineFormat, |_|true);
+		setConfig_String(config,"lineFormat",&mut newConfig.lineFormat, |a| FormaterCompile(&a.to_string()).is_ok());
+		let mut multiline = newConfig.multiline.name().to_string();
+		setConfig_String(config,"multiline",&mut multiline, |a| FormaterMultiline::fromName(a).is_some());
+		newConfig.multiline = FormaterMultiline::fromName(&multiline).unwrap_or(newConfig.multiline);
+		setConfig_boolean(config,"interpretEscapes",&mut newConfig.interpretEscapes);
 		setConfig_boolean(config,"byHour",&mut newConfig.byHour);
 		setConfig_boolean(config,"bySrc",&mut newConfig.bySrc);
 		setConfig_boolean(config,"byThreadId",&mut newConfig.byThreadId);
@@ -0,0 +1,323 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use anyhow::Result;
+use time::OffsetDateTime;
+use crate::components::context::Context;
+use crate::components::json::JsonObject;
+use crate::components::level::Level;
+use crate::components::trace::OneTrace;
+use crate::modules::gelf_config::{GelfCompression, GelfConfig};
+use crate::modules::module_abstract::ModuleAbstract;
+use crate::modules::network::NetworkSender;
+use crate::modules::network_config::NetworkProtocol;
+
+/// size of the header of each udp chunk (magic bytes, message id, sequence number, sequence count)
+const GELF_CHUNK_HEADER: usize = 12;
+/// maximum number of chunks for one message (graylog discards bigger messages)
+const GELF_CHUNK_MAX: usize = 128;
+/// additional fields written by the module (without the "_"), extras with these names are renamed "_extra_<name>"
+const GELF_FIELDS: [&str; 8] = ["id", "level_name", "file", "line", "causes", "sample_rate", "thread", "context"];
+/// short_message of an empty trace (GELF reject an empty one)
+const GELF_EMPTY_MESSAGE: &str = "(empty message)";
+/// end of a message truncated to fit in GELF_CHUNK_MAX chunks
+const GELF_TRUNCATED: &str = " (truncated)";
+
+static MESSAGE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// send traces as GELF 1.1 messages to Graylog (or any GELF compatible input)
+/// udp messages are chunked (and optionally compressed), tcp messages are null byte delimited
+pub struct Gelf
+{
+	_name: String,
+	_configs: GelfConfig,
+	_sender: NetworkSender,
+}
+
+impl Gelf
+{
+	pub fn new(config: GelfConfig) -> Gelf {
+		let sender = NetworkSender::new(
+			config.address.clone(),
+			config.protocol,
+			config.connectTimeout,
+			(config.reconnectDelayMin, config.reconnectDelayMax),
+			config.buffer.clone(),
+			config.bufferMaxTraces,
+		);
+		return Gelf{
+			_name: String::new(),
+			_configs: config,
+			_sender: sender,
+		};
+	}
+
+	/// GELF json representation of a trace
+	pub fn encode(&self, trace: &OneTrace) -> String
+	{
+		return self.encode_truncated(trace, None);
+	}
+
+	/// GELF json representation of a trace, short_message and full_message being truncated to maxLength bytes
+	fn encode_truncated(&self, trace: &OneTrace, maxLength: Option<usize>) -> String
+	{
+		let shortMessage = if(trace.message.trim().is_empty()) {Cow::Borrowed(GELF_EMPTY_MESSAGE)} else {Self::truncate(&trace.message, maxLength)};
+		let mut json = JsonObject::new();
+		json.string("version", "1.1")
+			.string("host", &self._configs.host)
+			.string("short_message", &shortMessage);
+
+		// full message only when there is something more to show
+		if(!trace.backtraces.is_empty())
+		{
+			let mut fullMessage = trace.message.clone();
+			for one in &trace.backtraces
+			{
+				let _ = write!(fullMessage, "\n{}", one);
+			}
+			json.string("full_message", &Self::truncate(&fullMessage, maxLength));
+		}
+
+		json.number("timestamp", trace.date.unix_timestamp_nanos() as f64 / 1_000_000_000.0)
+			.number("level", Self::syslogLevel(&trace.level) as f64)
+			.string("_level_name", &trace.level.to_string())
+			.string("_file", &trace.filename)
+			.number("_line", trace.fileline as f64);
+
+		// causes of a traced error, one per line
+		if(!trace.causes.is_empty())
+		{
+			json.string("_causes", &trace.causes.join("\n"));
+		}
+		if(trace.sampleRate < 1.0)
+		{
+			json.number("_sample_rate", trace.sampleRate);
+		}
+
+		if let Some(thread) = trace.context.threadName_get()
+		{
+			json.string("_thread", thread);
+		}
+		if let Some(context) = trace.context.name_get()
+		{
+			json.string("_context", context);
+		}
+
+		let mut extras = trace.context.extra_getAll().iter().collect::<Vec<_>>();
+		extras.sort();
+		for (key, value) in extras
+		{
+			json.string(&Self::additionalField(key), value);
+		}
+
+		return json.build();
+	}
+
+	/// syslog severity of a level
+	pub fn syslogLevel(level: &Level) -> u8
+	{
+		match level
+		{
+			Level::DEBUG => 7,
+			Level::DEBUGERR => 7,
+			Level::NORMAL => 6,
+			Level::NOTICE => 5,
+			Level::NOTICEDERR => 5,
+			Level::WARNING => 4,
+			Level::ERROR => 3,
+			Level::FATAL => 2,
+		}
+	}
+
+	/// text cut to maxLength bytes (on a char boundary), followed by GELF_TRUNCATED
+	fn truncate(text: &str, maxLength: Option<usize>) -> Cow<'_, str>
+	{
+		let Some(mut end) = maxLength.filter(|maxLength| *maxLength < text.len()) else {return Cow::Borrowed(text)};
+		while (!text.is_char_boundary(end))
+		{
+			end -= 1;
+		}
+		return Cow::Owned(format!("{}{}", &text[..end], GELF_TRUNCATED));
+	}
+
+	/// name of an additional field, GELF only allow [\w\.\-] and reserve "_id"
+	/// extras named like a field of the module (or starting with "extra_") are prefixed by "extra_", so they can't replace it
+	fn additionalField(key: &str) -> String
+	{
+		let key = key.chars().map(|c| {
+			if(c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-') {c} else {'_'}
+		}).collect::<String>();
+
+		if(GELF_FIELDS.contains(&key.as_str()) || key.starts_with("extra_"))
+		{
+			return format!("_extra_{}", key);
+		}
+		return format!("_{}", key);
+	}
+
+	fn send(&self, trace: &OneTrace)
+	{
+		let frames = match self._configs.protocol
+		{
+			NetworkProtocol::Tcp => {
+				let mut frame = self.encode(trace).into_bytes();
+				frame.push(0);
+				vec![frame]
+			}
+			NetworkProtocol::Udp => {
+				let Some(frames) = self.chunkTruncated(trace) else {return};
+				frames
+			}
+		};
+
+		self._sender.send(&frames);
+	}
+
+	/// udp chunks of a trace, its messages are truncated until they fit in GELF_CHUNK_MAX chunks
+	/// return None (with a warning) if the trace is too big even without messages
+	fn chunkTruncated(&self, trace: &OneTrace) -> Option<Vec<Vec<u8>>>
+	{
+		let capacity = GELF_CHUNK_MAX * self.chunkData();
+		// the full message (if any) is the longest one, both are truncated to the same length
+		let truncated = if(trace.backtraces.is_empty()) {1} else {2};
+		let longest = trace.message.len() + trace.backtraces.iter().map(|one| one.to_string().len() + 1).sum::<usize>();
+		let mut maxLength = None;
+		loop
+		{
+			let json = self.encode_truncated(trace, maxLength);
+			let payload = self.compress(json.into_bytes());
+			let overflow = payload.len().saturating_sub(capacity);
+			if let Some(frames) = self.chunk(payload)
+			{
+				if(maxLength.is_some())
+				{
+					self._sender.warn(&format!("message truncated to {} bytes to fit in {} chunks", maxLength.unwrap_or_default(), GELF_CHUNK_MAX));
+				}
+				return Some(frames);
+			}
+
+			let current = maxLength.unwrap_or(longest);
+			if(current == 0)
+			{
+				self._sender.warn(&format!("message dropped : bigger than {} chunks", GELF_CHUNK_MAX));
+				return None;
+			}
+			// compressed payloads don't shrink like the messages, remove at least 10% each time
+			maxLength = Some(current.saturating_sub(overflow.div_ceil(truncated).max(current / 10).max(1)));
+		}
+	}
+
+	/// data size of an udp chunk
+	fn chunkData(&self) -> usize
+	{
+		return self._configs.chunkSize.saturating_sub(GELF_CHUNK_HEADER).max(1);
+	}
+
+	fn compress(&self, payload: Vec<u8>) -> Vec<u8>
+	{
+		match self._configs.compression
+		{
+			GelfCompression::None => payload,
+			#[cfg(feature = "gelf_compression")]
+			GelfCompression::Gzip => {
+				use std::io::Write;
+				let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+				if(encoder.write_all(&payload).is_err()) {return payload;}
+				encoder.finish().unwrap_or(payload)
+			}
+			#[cfg(feature = "gelf_compression")]
+			GelfCompression::Zlib => {
+				use std::io::Write;
+				let mut encoder = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+				if(encoder.write_all(&payload).is_err()) {return payload;}
+				encoder.finish().unwrap_or(payload)
+			}
+		}
+	}
+
+	/// split an udp payload into GELF chunks, return None if the message is too big
+	fn chunk(&self, payload: Vec<u8>) -> Option<Vec<Vec<u8>>>
+	{
+		if(payload.len() <= self._configs.chunkSize)
+		{
+			return Some(vec![payload]);
+		}
+
+		let chunkData = self.chunkData();
+		let count = payload.len().div_ceil(chunkData);
+		if(count > GELF_CHUNK_MAX)
+		{
+			return None;
+		}
+
+		let messageId = (OffsetDateTime::now_utc().unix_timestamp_nanos() as u64) ^ MESSAGE_ID.fetch_add(1, Ordering::Relaxed).rotate_left(32);
+		return Some(payload.chunks(chunkData).enumerate().map(|(sequence, data)| {
+			let mut chunk = Vec::with_capacity(GELF_CHUNK_HEADER + data.len());
+			chunk.extend_from_slice(&[0x1e, 0x0f]);
+			chunk.extend_from_slice(&messageId.to_be_bytes());
+			chunk.push(sequence as u8);
+			chunk.push(count as u8);
+			chunk.extend_from_slice(data);
+			chunk
+		}).collect());
+	}
+}
+
+impl ModuleAbstract for Gelf
+{
+	fn name_set(&mut self, moduleName: String) -> Result<()> {
+		self._name = moduleName;
+		return Ok(());
+	}
+
+	fn name_get(&self) -> Result<String> {
+		return Ok(self._name.clone());
+	}
+
+	fn event_onDebug(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onDebugErr(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onNormal(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onNotice(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onNoticeErr(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onWarning(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onError(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onFatal(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onContextExit(&self, _: &Context) {}
+
+	fn event_onGlobalExit(&self) {
+		// last chance to send buffered messages
+		self._sender.flush();
+	}
+}
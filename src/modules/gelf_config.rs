@@ -0,0 +1,121 @@
+use std::time::Duration;
+use crate::modules::network_config::{NetworkBuffer, NetworkProtocol};
+
+#[cfg(feature = "hconfig")]
+use Hconfig::tinyjson::JsonValue;
+
+/// compression of udp messages (tcp messages are never compressed)
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum GelfCompression
+{
+	None,
+	#[cfg(feature = "gelf_compression")]
+	Gzip,
+	#[cfg(feature = "gelf_compression")]
+	Zlib,
+}
+
+pub struct GelfConfig
+{
+	/// graylog input "host:port"
+	pub address: String,
+	/// udp (chunked) or tcp (null byte delimited)
+	pub protocol: NetworkProtocol,
+	/// "host" field of each message (default to the machine hostname)
+	pub host: String,
+	/// compression of udp messages
+	pub compression: GelfCompression,
+	/// maximum size of an udp datagram, bigger messages are chunked (up to 128 chunks)
+	pub chunkSize: usize,
+	/// max time to wait when connecting
+	pub connectTimeout: Duration,
+	/// first delay before retrying a failed connection
+	pub reconnectDelayMin: Duration,
+	/// the delay is doubled at each failure, up to this value
+	pub reconnectDelayMax: Duration,
+	/// where to keep messages while disconnected
+	pub buffer: NetworkBuffer,
+	/// maximum number of messages (all the chunks of a trace) kept while disconnected (in memory the oldest are dropped, on disk the new ones)
+	pub bufferMaxTraces: usize,
+}
+
+impl GelfConfig
+{
+	/// best effort hostname, without additional dependency
+	pub fn hostname() -> String
+	{
+		if let Ok(host) = std::env::var("HOSTNAME").or_else(|_| std::env::var("COMPUTERNAME")) && !host.trim().is_empty()
+		{
+			return host.trim().to_string();
+		}
+		if let Ok(host) = std::fs::read_to_string("/etc/hostname") && !host.trim().is_empty()
+		{
+			return host.trim().to_string();
+		}
+		return "localhost".to_string();
+	}
+}
+
+impl Default for GelfConfig
+{
+	fn default() -> Self {
+		return GelfConfig{
+			address: "127.0.0.1:12201".to_string(),
+			protocol: NetworkProtocol::Udp,
+			host: Self::hostname(),
+			compression: GelfCompression::None,
+			chunkSize: 1420,
+			connectTimeout: Duration::from_secs(1),
+			reconnectDelayMin: Duration::from_millis(500),
+			reconnectDelayMax: Duration::from_secs(30),
+			buffer: NetworkBuffer::Memory,
+			bufferMaxTraces: 10000,
+		};
+	}
+}
+
+#[cfg(feature = "hconfig")]
+impl GelfConfig
+{
+	pub fn create_from_hconfig(configs: &mut JsonValue, mut newConfig: Self) -> Self
+	{
+		use crate::modules::utils_hconfig::setConfig_String;
+
+		let JsonValue::Object(config) = configs else {return newConfig};
+		setConfig_String(config,"address",&mut newConfig.address, |_|true);
+		setConfig_String(config,"host",&mut newConfig.host, |a| !a.is_empty());
+
+		let mut protocol = match newConfig.protocol {
+			NetworkProtocol::Tcp => "tcp",
+			NetworkProtocol::Udp => "udp",
+		}.to_string();
+		setConfig_String(config,"protocol",&mut protocol, |a| a == "tcp" || a == "udp");
+		newConfig.protocol = if(protocol == "tcp") {NetworkProtocol::Tcp} else {NetworkProtocol::Udp};
+
+		let mut compression = match newConfig.compression {
+			GelfCompression::None => "none",
+			#[cfg(feature = "gelf_compression")]
+			GelfCompression::Gzip => "gzip",
+			#[cfg(feature = "gelf_compression")]
+			GelfCompression::Zlib => "zlib",
+		}.to_string();
+		setConfig_String(config,"compression",&mut compression, |a| a == "none" || (cfg!(feature = "gelf_compression") && (a == "gzip" || a == "zlib")));
+		newConfig.compression = match compression.as_str() {
+			#[cfg(feature = "gelf_compression")]
+			"gzip" => GelfCompression::Gzip,
+			#[cfg(feature = "gelf_compression")]
+			"zlib" => GelfCompression::Zlib,
+			_ => GelfCompression::None,
+		};
+
+		// an empty bufferPath mean a memory buffer
+		let mut bufferPath = match &newConfig.buffer {
+			NetworkBuffer::Memory => "".to_string(),
+			NetworkBuffer::Disk(path) => path.clone(),
+		};
+		setConfig_String(config,"bufferPath",&mut bufferPath, |_|true);
+		newConfig.buffer = if(bufferPath.is_empty()) {NetworkBuffer::Memory} else {NetworkBuffer::Disk(bufferPath)};
+
+		return newConfig;
+	}
+}
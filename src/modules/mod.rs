@@ -11,6 +11,16 @@ pub mod command_line;
 #[cfg(feature = "default_module")]
 pub mod command_line_config;
 
+#[cfg(feature = "default_module")]
+pub mod network;
+#[cfg(feature = "default_module")]
+pub mod network_config;
+
+#[cfg(feature = "default_module")]
+pub mod gelf;
+#[cfg(feature = "default_module")]
+pub mod gelf_config;
+
 #[cfg(feature = "hconfig")]
 pub mod utils_hconfig {
 	use std::collections::HashMap;
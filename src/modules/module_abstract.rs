@@ -16,7 +16,15 @@ pub trait ModuleAbstract: Sync + Send
 	fn event_onError(&self, trace: &OneTrace);
 	fn event_onFatal(&self, trace: &OneTrace);
 
+	/// a span is entered (once per span), trace.context is the resolved context of the span
+	fn event_onSpanEnter(&self, _trace: &OneTrace) {}
+	/// a span is exited (once per span), trace.spanDuration is its duration
+	fn event_onSpanExit(&self, _trace: &OneTrace) {}
+
+	/// a context is exited (once per context) : its span is dropped, after its last traces (context is the resolved context of the span, see Context::id_get),
+	/// or the global context at HTracer::drop()
 	fn event_onContextExit(&self, context: &Context);
-	
+
+	/// Htrace is stopped (HTracer::drop()), after the last traces
 	fn event_onGlobalExit(&self);
 }
\ No newline at end of file
@@ -0,0 +1,553 @@
+use std::collections::VecDeque;
+use std::fs::{create_dir_all, remove_file, OpenOptions};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use crate::Errors;
+use parking_lot::Mutex;
+use time::format_description::well_known::Rfc3339;
+use crate::components::context::Context;
+use crate::components::formater::{Formatter, TemplateFormatter};
+use crate::components::json::JsonObject;
+use crate::components::trace::OneTrace;
+use crate::modules::module_abstract::ModuleAbstract;
+use crate::modules::network_config::{NetworkBuffer, NetworkConfig, NetworkEncoding, NetworkFraming, NetworkProtocol};
+
+/// send traces to a remote aggregator, over tcp or udp
+/// while the connection is down, traces are buffered (see NetworkConfig.buffer) and sent on reconnection
+pub struct Network
+{
+	_name: String,
+	_configs: NetworkConfig,
+	_formatter: Box<dyn Formatter>,
+	_sender: NetworkSender,
+	_render: Mutex<NetworkRender>,
+}
+
+/// buffers reused to encode and frame each trace
+#[derive(Default)]
+struct NetworkRender
+{
+	text: String,
+	frame: Vec<u8>,
+}
+
+impl Network
+{
+	/// create the module
+	/// panic if the lineFormat cannot be compiled, see try_new()
+	pub fn new(config: NetworkConfig) -> Network {
+		return match Self::try_new(config) {
+			Ok(module) => module,
+			Err(err) => panic!("[Htrace] {}", err),
+		};
+	}
+
+	/// create the module, return an error if the lineFormat cannot be compiled
+	pub fn try_new(mut config: NetworkConfig) -> Result<Network, Errors> {
+		let formatter = match config.formatter.take() {
+			Some(formatter) => formatter,
+			None => Box::new(TemplateFormatter::with_options(&config.lineFormat, config.formaterOptions())?),
+		};
+		let sender = NetworkSender::new(
+			config.address.clone(),
+			config.protocol,
+			config.connectTimeout,
+			(config.reconnectDelayMin, config.reconnectDelayMax),
+			config.buffer.clone(),
+			config.bufferMaxTraces,
+		);
+		return Ok(Network{
+			_name: String::new(),
+			_configs: config,
+			_formatter: formatter,
+			_sender: sender,
+			_render: Mutex::new(NetworkRender::default()),
+		});
+	}
+
+	/// encode a trace using the configured encoding
+	pub fn encode(&self, trace: &OneTrace) -> String
+	{
+		let mut output = String::new();
+		self.encode_into(trace, &mut output);
+		return output;
+	}
+
+	/// encode a trace at the end of output (allow reusing a buffer)
+	pub fn encode_into(&self, trace: &OneTrace, output: &mut String)
+	{
+		match self._configs.encoding
+		{
+			NetworkEncoding::Rendered => self._formatter.format_into(trace, None, output),
+			NetworkEncoding::Json => output.push_str(&Self::encodeJson(trace)),
+		}
+	}
+
+	/// json representation of a trace
+	pub fn encodeJson(trace: &OneTrace) -> String
+	{
+		let mut json = JsonObject::new();
+		json.string("timestamp", &trace.date.format(&Rfc3339).unwrap_or_default())
+			.number("sequence", trace.sequence as f64)
+			.string("level", &trace.level.to_string())
+			.number("levelValue", trace.level.tou8() as f64)
+			.string("message", &trace.message)
+			.string("file", &trace.filename)
+			.number("line", trace.fileline as f64)
+			.number("column", trace.fileColumn as f64)
+			.string("module", trace.modulePath)
+			.string("function", trace.funcName)
+			.string("thread", &trace.context.threadName_get().clone().unwrap_or_default())
+			.string("context", &trace.context.name_get().clone().unwrap_or_default())
+			.stringMap("extras", trace.context.extra_getAll());
+
+		// spans of the trace, from the root one (like {span_path})
+		if let Some(spanId) = trace.context.spanId_get()
+		{
+			let spanPath = trace.context.spanPath_get().iter().map(|span| span.to_string()).collect::<Vec<_>>();
+			json.number("spanId", spanId as f64)
+				.stringArray("spanPath", spanPath.iter().map(|one| one.as_str()));
+		}
+
+		let backtraces = trace.backtraces.iter().map(|one| one.to_string()).collect::<Vec<_>>();
+		json.stringArray("backtraces", backtraces.iter().map(|one| one.as_str()));
+		if(!trace.causes.is_empty())
+		{
+			json.stringArray("causes", trace.causes.iter().map(|one| one.as_str()));
+		}
+		if(trace.sampleRate < 1.0)
+		{
+			json.number("sampleRate", trace.sampleRate);
+		}
+
+		return json.build();
+	}
+
+	fn send(&self, trace: &OneTrace)
+	{
+		let mut render = self._render.lock();
+		let render = &mut *render;
+		render.text.clear();
+		self.encode_into(trace, &mut render.text);
+		self.frame_into(render.text.as_bytes(), &mut render.frame);
+		self._sender.send(&[render.frame.as_slice()]);
+	}
+
+	/// replace frame by an encoded trace with its transport framing
+	fn frame_into(&self, payload: &[u8], frame: &mut Vec<u8>)
+	{
+		frame.clear();
+		match (self._configs.protocol, self._configs.framing)
+		{
+			(NetworkProtocol::Udp, _) => frame.extend_from_slice(payload),
+			(_, NetworkFraming::Newline) => {
+				frame.extend_from_slice(payload);
+				frame.push(b'\n');
+			}
+			(_, NetworkFraming::LengthPrefixed) => {
+				frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+				frame.extend_from_slice(payload);
+			}
+		}
+	}
+}
+
+enum NetworkSocket
+{
+	Tcp(TcpStream),
+	Udp(UdpSocket),
+}
+
+/// biggest udp datagram (ipv4), bigger frames cannot be sent
+const UDP_MAX_DATAGRAM: usize = 65507;
+
+/// frames of one trace (one frame, or the chunks of a Gelf udp message), buffered and dropped together
+type NetworkMessage = Vec<Vec<u8>>;
+
+struct NetworkState
+{
+	socket: Option<NetworkSocket>,
+	retryAt: Option<Instant>,
+	retryDelay: Duration,
+	pending: VecDeque<NetworkMessage>,
+	/// messages in the disk buffer (None until the file is read, it can remain from a previous run)
+	diskMessages: Option<usize>,
+	/// messages dropped because the buffer was full, reported once sent again
+	dropped: u64,
+}
+
+enum NetworkWrite
+{
+	Sent,
+	/// the frame can never be sent (like a too big udp datagram)
+	Dropped,
+	/// the connection failed, the frame must be buffered
+	Failed,
+}
+
+/// connection handling shared by network based modules (Network, Gelf)
+/// frames are sent as-is: a tcp frame must already contain its framing, an udp frame is one datagram
+/// while disconnected, the frames of a trace are buffered together and the connection is retried with an exponential backoff
+pub(crate) struct NetworkSender
+{
+	_address: String,
+	_protocol: NetworkProtocol,
+	_connectTimeout: Duration,
+	_reconnectDelay: (Duration, Duration),
+	_buffer: NetworkBuffer,
+	_bufferMaxTraces: usize,
+	_state: Mutex<NetworkState>,
+}
+
+impl NetworkSender
+{
+	pub fn new(address: String, protocol: NetworkProtocol, connectTimeout: Duration, reconnectDelay: (Duration, Duration), buffer: NetworkBuffer, bufferMaxTraces: usize) -> Self
+	{
+		return NetworkSender {
+			_address: address,
+			_protocol: protocol,
+			_connectTimeout: connectTimeout,
+			_reconnectDelay: reconnectDelay,
+			_buffer: buffer,
+			_bufferMaxTraces: bufferMaxTraces,
+			_state: Mutex::new(NetworkState {
+				socket: None,
+				retryAt: None,
+				retryDelay: reconnectDelay.0,
+				pending: VecDeque::new(),
+				diskMessages: None,
+				dropped: 0,
+			}),
+		};
+	}
+
+	/// send the frames of one trace, or bufferize them if the connection is down
+	pub fn send<F: AsRef<[u8]>>(&self, frames: &[F])
+	{
+		if(!frames.iter().all(|frame| self.isSendable(frame.as_ref())))
+		{
+			return;
+		}
+
+		let mut state = self._state.lock();
+		if(self.connect(&mut state, false) && self.flushPending(&mut state) && self.writeMessage(&mut state, frames))
+		{
+			return;
+		}
+		self.bufferize(&mut state, frames.iter().map(|frame| frame.as_ref().to_vec()).collect());
+	}
+
+	/// try to send buffered frames, ignoring the backoff delay
+	pub fn flush(&self)
+	{
+		let mut state = self._state.lock();
+		if(self.connect(&mut state, true))
+		{
+			self.flushPending(&mut state);
+		}
+	}
+
+	/// return true if connected (try to connect if the backoff delay is elapsed, or if forced)
+	fn connect(&self, state: &mut NetworkState, force: bool) -> bool
+	{
+		if(state.socket.is_some())
+		{
+			return true;
+		}
+
+		if let Some(retryAt) = state.retryAt && !force && Instant::now() < retryAt
+		{
+			return false;
+		}
+
+		let addresses = self._address.to_socket_addrs().map(|x| x.collect::<Vec<_>>()).unwrap_or_default();
+		for address in addresses
+		{
+			if let Some(socket) = self.connectTo(address)
+			{
+				state.socket = Some(socket);
+				state.retryAt = None;
+				state.retryDelay = self._reconnectDelay.0;
+				return true;
+			}
+		}
+
+		self.disconnect(state);
+		return false;
+	}
+
+	fn connectTo(&self, address: SocketAddr) -> Option<NetworkSocket>
+	{
+		match self._protocol
+		{
+			NetworkProtocol::Tcp => {
+				let stream = TcpStream::connect_timeout(&address, self._connectTimeout).ok()?;
+				let _ = stream.set_nodelay(true);
+				let _ = stream.set_write_timeout(Some(self._connectTimeout));
+				return Some(NetworkSocket::Tcp(stream));
+			}
+			NetworkProtocol::Udp => {
+				let local = if(address.is_ipv4()) {"0.0.0.0:0"} else {"[::]:0"};
+				let socket = UdpSocket::bind(local).ok()?;
+				socket.connect(address).ok()?;
+				return Some(NetworkSocket::Udp(socket));
+			}
+		}
+	}
+
+	/// drop the connection and schedule the next retry (exponential backoff)
+	fn disconnect(&self, state: &mut NetworkState)
+	{
+		state.socket = None;
+		state.retryAt = Some(Instant::now() + state.retryDelay);
+		state.retryDelay = (state.retryDelay * 2).min(self._reconnectDelay.1);
+	}
+
+	/// send the frames of a trace, return false if the connection failed (the whole message must be buffered, even if some frames were sent)
+	/// the rest of a message is dropped if one of its frames can never be sent
+	fn writeMessage<F: AsRef<[u8]>>(&self, state: &mut NetworkState, frames: &[F]) -> bool
+	{
+		for frame in frames
+		{
+			match self.write(state, frame.as_ref())
+			{
+				NetworkWrite::Sent => {}
+				NetworkWrite::Dropped => return true,
+				NetworkWrite::Failed => return false,
+			}
+		}
+		return true;
+	}
+
+	/// send a frame, the connection is dropped on failure:
+	/// a tcp frame can be partially written, it must be sent again on a new connection
+	fn write(&self, state: &mut NetworkState, frame: &[u8]) -> NetworkWrite
+	{
+		let result = match &mut state.socket
+		{
+			None => Err(std::io::ErrorKind::NotConnected.into()),
+			Some(NetworkSocket::Tcp(stream)) => stream.write_all(frame),
+			Some(NetworkSocket::Udp(socket)) => socket.send(frame).map(|_| ()),
+		};
+		let Err(err) = result else {return NetworkWrite::Sent};
+
+		if(Self::isPermanent(&err))
+		{
+			self.warn(&format!("frame of {} bytes dropped : {}", frame.len(), err));
+			return NetworkWrite::Dropped;
+		}
+		self.disconnect(state);
+		return NetworkWrite::Failed;
+	}
+
+	/// the error will happen again for this frame (it must not be sent again)
+	fn isPermanent(err: &std::io::Error) -> bool
+	{
+		// EMSGSIZE (datagram too big for the socket) has no ErrorKind
+		let code = err.raw_os_error();
+		let tooLong = (cfg!(any(target_os = "linux", target_os = "android")) && code == Some(90))
+			|| (cfg!(any(target_vendor = "apple", target_os = "freebsd", target_os = "openbsd", target_os = "netbsd", target_os = "dragonfly")) && code == Some(40))
+			|| (cfg!(windows) && code == Some(10040));
+		return tooLong || matches!(err.kind(), std::io::ErrorKind::InvalidInput | std::io::ErrorKind::InvalidData);
+	}
+
+	/// an udp frame must fit in one datagram, bigger frames are dropped
+	fn isSendable(&self, frame: &[u8]) -> bool
+	{
+		if(self._protocol == NetworkProtocol::Udp && frame.len() > UDP_MAX_DATAGRAM)
+		{
+			self.warn(&format!("frame of {} bytes dropped : bigger than an udp datagram ({} bytes)", frame.len(), UDP_MAX_DATAGRAM));
+			return false;
+		}
+		return true;
+	}
+
+	/// report a trace that cannot be sent as is
+	pub fn warn(&self, message: &str)
+	{
+		eprintln!("[Htrace] network {} : {}", self._address, message);
+	}
+
+	/// keep the frames of a trace until the next successful connection, up to bufferMaxTraces:
+	/// in memory the oldest traces are dropped, on disk the new ones (the file is only appended)
+	fn bufferize(&self, state: &mut NetworkState, message: NetworkMessage)
+	{
+		match &self._buffer
+		{
+			NetworkBuffer::Memory => {
+				state.pending.push_back(message);
+				while (state.pending.len() > self._bufferMaxTraces)
+				{
+					state.pending.pop_front();
+					state.dropped += 1;
+				}
+			}
+			NetworkBuffer::Disk(path) => {
+				let count = *state.diskMessages.get_or_insert_with(|| Self::disk_read(path).len());
+				if(count >= self._bufferMaxTraces)
+				{
+					state.dropped += 1;
+					return;
+				}
+
+				if let Some(parent) = Path::new(path).parent()
+				{
+					let _ = create_dir_all(parent);
+				}
+				let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) else {return};
+				let mut record = vec![];
+				Self::disk_record(&message, &mut record);
+				if(file.write_all(&record).is_ok())
+				{
+					state.diskMessages = Some(count + 1);
+				}
+			}
+		}
+	}
+
+	/// send buffered traces, stop at the first failure (unsent traces stay buffered), return true if all were sent
+	fn flushPending(&self, state: &mut NetworkState) -> bool
+	{
+		while let Some(message) = state.pending.pop_front()
+		{
+			if(!self.writeMessage(state, &message))
+			{
+				state.pending.push_front(message);
+				return false;
+			}
+		}
+
+		if let NetworkBuffer::Disk(path) = &self._buffer && state.diskMessages != Some(0)
+		{
+			let records = Self::disk_read(path);
+			for (index, message) in records.iter().enumerate()
+			{
+				if(!self.writeMessage(state, message))
+				{
+					// rewrite the unsent part
+					state.diskMessages = Self::disk_write(path, &records[index..]).ok().map(|_| records.len() - index);
+					return false;
+				}
+			}
+			let _ = remove_file(path);
+			state.diskMessages = Some(0);
+		}
+
+		if(state.dropped > 0)
+		{
+			self.warn(&format!("{} traces dropped while disconnected (buffer full)", state.dropped));
+			state.dropped = 0;
+		}
+		return true;
+	}
+
+	/// a message is stored with its number of frames, then each frame with its length (udp datagrams are split again)
+	fn disk_record(message: &NetworkMessage, output: &mut Vec<u8>)
+	{
+		output.extend_from_slice(&(message.len() as u32).to_be_bytes());
+		for frame in message
+		{
+			output.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+			output.extend_from_slice(frame);
+		}
+	}
+
+	/// messages of the disk buffer, a truncated last record (interrupted write) is dropped
+	fn disk_read(path: &str) -> Vec<NetworkMessage>
+	{
+		let mut content = vec![];
+		let Ok(mut file) = OpenOptions::new().read(true).open(path) else {return vec![]};
+		if(file.read_to_end(&mut content).is_err())
+		{
+			return vec![];
+		}
+
+		let readU32 = |offset: usize| content.get(offset..offset + 4).map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize);
+		let mut records = vec![];
+		let mut offset = 0;
+		'records: while let Some(count) = readU32(offset)
+		{
+			let mut message = Vec::with_capacity(count.min(128));
+			let mut recordOffset = offset + 4;
+			for _ in 0..count
+			{
+				let Some(size) = readU32(recordOffset) else {break 'records};
+				let Some(frame) = content.get(recordOffset + 4..recordOffset + 4 + size) else {break 'records};
+				message.push(frame.to_vec());
+				recordOffset += 4 + size;
+			}
+			records.push(message);
+			offset = recordOffset;
+		}
+		return records;
+	}
+
+	fn disk_write(path: &str, records: &[NetworkMessage]) -> std::io::Result<()>
+	{
+		let mut content = vec![];
+		records.iter().for_each(|message| Self::disk_record(message, &mut content));
+		return std::fs::write(path, content);
+	}
+}
+
+impl ModuleAbstract for Network
+{
+	fn name_set(&mut self, moduleName: String) -> Result<()> {
+		self._name = moduleName;
+		return Ok(());
+	}
+
+	fn name_get(&self) -> Result<String> {
+		return Ok(self._name.clone());
+	}
+
+	fn event_onDebug(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onDebugErr(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onNormal(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onNotice(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onNoticeErr(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onWarning(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onError(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onFatal(&self, trace: &OneTrace)
+	{
+		self.send(trace);
+	}
+
+	fn event_onContextExit(&self, _: &Context) {}
+
+	fn event_onGlobalExit(&self) {
+		// last chance to send buffered traces
+		self._sender.flush();
+	}
+}
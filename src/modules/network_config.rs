@@ -0,0 +1,162 @@
+use std::time::Duration;
+use crate::components::formater::{FormaterMultiline, FormaterOptions, Formatter};
+
+#[cfg(feature = "hconfig")]
+use Hconfig::tinyjson::JsonValue;
+
+/// transport used to send traces
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum NetworkProtocol
+{
+	/// stream of framed traces, reconnect automatically
+	Tcp,
+	/// one datagram per trace (no framing)
+	Udp,
+}
+
+/// how traces are delimited on a TCP stream
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum NetworkFraming
+{
+	/// each trace end with a "\n" (line returns inside a trace are already replaced by lineReturn or escaped in json)
+	Newline,
+	/// each trace is preceded by its length, as a big endian u32
+	LengthPrefixed,
+}
+
+/// content of each sent trace
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum NetworkEncoding
+{
+	/// the trace rendered with lineFormat (like File or CommandLine)
+	Rendered,
+	/// a json object (timestamp, level, message, file, line, thread, context, extras, backtraces, and causes of traced errors)
+	Json,
+}
+
+/// where traces are kept while the connection is down
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum NetworkBuffer
+{
+	/// keep traces in memory (lost if the program stop before reconnection)
+	Memory,
+	/// append traces to this file (kept between runs), the file is sent then removed on reconnection
+	Disk(String),
+}
+
+pub struct NetworkConfig
+{
+	/// destination "host:port"
+	pub address: String,
+	/// tcp or udp
+	pub protocol: NetworkProtocol,
+	/// framing of traces (only used with tcp)
+	pub framing: NetworkFraming,
+	/// rendered line or json
+	pub encoding: NetworkEncoding,
+	/// adding string when a trace have à return char "\n"/"\r" (only for Rendered encoding)
+	pub lineReturn: String,
+	/// format of the trace, view FormaterCompile for the syntax and available variables (only for Rendered encoding)
+	pub lineFormat: String,
+	/// rendering of multi-line messages : indented with lineReturn (default), escaped as "\\n", or one line per message line
+	pub multiline: FormaterMultiline,
+	/// also consider escaped "\\n" and "\\r" in messages as line returns (default true)
+	pub interpretEscapes: bool,
+	/// max time to wait when connecting
+	pub connectTimeout: Duration,
+	/// first delay before retrying a failed connection
+	pub reconnectDelayMin: Duration,
+	/// the delay is doubled at each failure, up to this value
+	pub reconnectDelayMax: Duration,
+	/// where to keep traces while disconnected
+	pub buffer: NetworkBuffer,
+	/// maximum number of traces kept while disconnected (in memory the oldest are dropped, on disk the new ones)
+	pub bufferMaxTraces: usize,
+	/// custom formatter, replacing the TemplateFormatter built from lineFormat and lineReturn
+	pub formatter: Option<Box<dyn Formatter>>,
+}
+
+impl Default for NetworkConfig
+{
+	fn default() -> Self {
+		return NetworkConfig{
+			address: "127.0.0.1:5170".to_string(),
+			protocol: NetworkProtocol::Tcp,
+			framing: NetworkFraming::Newline,
+			encoding: NetworkEncoding::Json,
+			lineReturn: " | ".to_string(),
+			lineFormat: "{time} {lvl} ({thread:>, }{context:>, }{file}:l{line}) : {msg}".to_string(),
+			multiline: FormaterMultiline::Indent,
+			interpretEscapes: true,
+			connectTimeout: Duration::from_secs(1),
+			reconnectDelayMin: Duration::from_millis(500),
+			reconnectDelayMax: Duration::from_secs(30),
+			buffer: NetworkBuffer::Memory,
+			bufferMaxTraces: 10000,
+			formatter: None,
+		};
+	}
+}
+
+impl NetworkConfig
+{
+	/// options of the default formatter
+	pub fn formaterOptions(&self) -> FormaterOptions
+	{
+		return FormaterOptions {
+			lineReturn: self.lineReturn.clone(),
+			multiline: self.multiline,
+			interpretEscapes: self.interpretEscapes,
+		};
+	}
+}
+
+#[cfg(feature = "hconfig")]
+impl NetworkConfig
+{
+	pub fn create_from_hconfig(configs: &mut JsonValue, mut newConfig: Self) -> Self
+	{
+		use crate::components::formater::FormaterCompile;
+		use crate::modules::utils_hconfig::{setConfig_String, setConfig_boolean};
+
+		let JsonValue::Object(config) = configs else {return newConfig};
+		setConfig_String(config,"address",&mut newConfig.address, |_|true);
+		setConfig_String(config,"lineReturn",&mut newConfig.lineReturn, |_|true);
+		setConfig_String(config,"lineFormat",&mut newConfig.lineFormat, |a| FormaterCompile(&a.to_string()).is_ok());
+		let mut multiline = newConfig.multiline.name().to_string();
+		setConfig_String(config,"multiline",&mut multiline, |a| FormaterMultiline::fromName(a).is_some());
+		newConfig.multiline = FormaterMultiline::fromName(&multiline).unwrap_or(newConfig.multiline);
+		setConfig_boolean(config,"interpretEscapes",&mut newConfig.interpretEscapes);
+
+		let mut protocol = match newConfig.protocol {
+			NetworkProtocol::Tcp => "tcp",
+			NetworkProtocol::Udp => "udp",
+		}.to_string();
+		setConfig_String(config,"protocol",&mut protocol, |a| a == "tcp" || a == "udp");
+		newConfig.protocol = if(protocol == "udp") {NetworkProtocol::Udp} else {NetworkProtocol::Tcp};
+
+		let mut framing = match newConfig.framing {
+			NetworkFraming::Newline => "newline",
+			NetworkFraming::LengthPrefixed => "length",
+		}.to_string();
+		setConfig_String(config,"framing",&mut framing, |a| a == "newline" || a == "length");
+		newConfig.framing = if(framing == "length") {NetworkFraming::LengthPrefixed} else {NetworkFraming::Newline};
+
+		let mut encoding = match newConfig.encoding {
+			NetworkEncoding::Rendered => "rendered",
+			NetworkEncoding::Json => "json",
+		}.to_string();
+		setConfig_String(config,"encoding",&mut encoding, |a| a == "rendered" || a == "json");
+		newConfig.encoding = if(encoding == "rendered") {NetworkEncoding::Rendered} else {NetworkEncoding::Json};
+
+		// an empty bufferPath mean a memory buffer
+		let mut bufferPath = match &newConfig.buffer {
+			NetworkBuffer::Memory => "".to_string(),
+			NetworkBuffer::Disk(path) => path.clone(),
+		};
+		setConfig_String(config,"bufferPath",&mut bufferPath, |_|true);
+		newConfig.buffer = if(bufferPath.is_empty()) {NetworkBuffer::Memory} else {NetworkBuffer::Disk(bufferPath)};
+
+		return newConfig;
+	}
+}
@@ -0,0 +1,62 @@
+use std::thread::JoinHandle;
+use crate::context_manager::ContextManager;
+use crate::thread_manager::ThreadManager;
+
+/// Spawn a named thread (like std::thread::spawn) that inherits a snapshot of the current context stack.
+/// The name is used as std thread name and as Htrace thread name.
+/// ```
+/// use Htrace::HTrace;
+///
+/// let worker = Htrace::thread::spawn("worker 1", || {
+///     // traced with the "worker 1" thread name, and the spawning thread contexts
+///     HTrace!("working");
+/// });
+/// ```
+pub fn spawn<F, T>(name: impl Into<String>, func: F) -> JoinHandle<T>
+	where F: FnOnce() -> T + Send + 'static,
+	      T: Send + 'static
+{
+	let name = name.into();
+	let stack = ContextManager::singleton().stack_get();
+
+	return std::thread::Builder::new()
+		.name(name.clone())
+		.spawn(move || {
+			ThreadManager::local_setName(name);
+			ContextManager::singleton().stack_swap(stack);
+			func()
+		})
+		.expect("failed to spawn thread");
+}
+
+/// set the Htrace name of the current thread (shown by the "{thread}" formater variable)
+pub fn set_thread_name(name: impl Into<String>)
+{
+	ThreadManager::local_setName(name);
+}
+
+/// get the displayed name of the current thread: its Htrace name, else the std thread name, else its id
+pub fn thread_name() -> String
+{
+	return ThreadManager::local_getDisplayName();
+}
+
+/// set an extra data for the current thread, usable by formaters like context extras (ex: "{extra[request_id]}")
+/// thread extras override global context extras, and are overridden by extras of the contexts (spans) of the thread
+/// they are not inherited by spawned threads
+pub fn extra_set(name: impl Into<String>, content: impl Into<String>)
+{
+	let _ = ThreadManager::local_setExtraDatas(name, content);
+}
+
+/// get an extra data of the current thread
+pub fn extra_get(name: impl Into<String>) -> Option<String>
+{
+	return ThreadManager::local_getExtraDatas(name);
+}
+
+/// remove an extra data of the current thread, return its previous value
+pub fn extra_remove(name: impl Into<String>) -> Option<String>
+{
+	return ThreadManager::local_removeExtraDatas(name);
+}
@@ -4,8 +4,9 @@ pub static MAIN_THREAD: &str = "MAIN";
 
 use std::cell::RefCell;
 use std::thread::AccessError;
+use crate::context_manager::ContextManager;
 
-/// used for storing thread name, useless for wasm
+/// used for storing thread name (display only, context stacks are stored in ContextManager), useless for wasm
 pub struct ThreadManager
 {
 }
@@ -23,10 +24,27 @@ impl ThreadManager
 		return Self::NAME.try_with(|a| a.borrow().clone()).unwrap_or_else(|_| None)
 	}
 
+	/// name displayed for the local thread: its Htrace name, else the std thread name, else its id
+	pub fn local_getDisplayName() -> String
+	{
+		if let Some(name) = Self::local_getName()
+		{
+			return name;
+		}
+
+		let current = std::thread::current();
+		return match current.name()
+		{
+			Some(name) => name.to_string(),
+			None => format!("{:?}", current.id()),
+		};
+	}
+
 	/// set a local thread name
 	pub fn local_setName(name: impl Into<String>)
 	{
 		Self::NAME.set(Some(name.into()));
+		ContextManager::cache_invalidate();
 	}
 
 	/// get local thread extra data
@@ -36,11 +54,26 @@ impl ThreadManager
 		return Self::EXTRADATAS.try_with(|a| a.borrow().get(&extraDataName).cloned()).unwrap_or_else(|_| None);
 	}
 
+	/// get all local thread extra data
+	pub fn local_getAllExtraDatas() -> HashMap<String,String>
+	{
+		return Self::EXTRADATAS.try_with(|a| a.borrow().clone()).unwrap_or_default();
+	}
+
+	/// remove local thread extra data
+	pub fn local_removeExtraDatas(extraDataName: impl Into<String>) -> Option<String>
+	{
+		let extraDataName= extraDataName.into();
+		ContextManager::cache_invalidate();
+		return Self::EXTRADATAS.try_with(|a| a.borrow_mut().remove(&extraDataName)).unwrap_or_else(|_| None);
+	}
+
 	/// set local thread extra data
 	pub fn local_setExtraDatas(extraDataName: impl Into<String>, content: impl Into<String>) -> Result<Option<String>, AccessError>
 	{
 		let extraDataName = extraDataName.into();
 		let content = content.into();
+		ContextManager::cache_invalidate();
 		return Self::EXTRADATAS.try_with(|a| a.borrow_mut().insert(extraDataName,content));
 	}
 }
\ No newline at end of file
@@ -0,0 +1,63 @@
+#![allow(dead_code)]
+#![allow(unused_parens)]
+
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+use Htrace::components::context::Context;
+use Htrace::components::trace::OneTrace;
+use Htrace::modules::module_abstract::ModuleAbstract;
+
+/// module keeping a description of each trace (by default "context name : message")
+pub struct Capture
+{
+	captured: Arc<Mutex<Vec<String>>>,
+	describe: fn(&OneTrace) -> String,
+}
+
+impl Capture
+{
+	pub fn new(captured: Arc<Mutex<Vec<String>>>) -> Self
+	{
+		return Self::with(captured, |trace| format!("{} : {}", trace.context.name_get().clone().unwrap_or_default(), trace.message));
+	}
+
+	pub fn with(captured: Arc<Mutex<Vec<String>>>, describe: fn(&OneTrace) -> String) -> Self
+	{
+		return Capture { captured, describe };
+	}
+
+	/// wait (max 5s) until the writer thread emitted "count" traces, and return them
+	pub fn wait(captured: &Arc<Mutex<Vec<String>>>, count: usize) -> Vec<String>
+	{
+		let timeout = Instant::now() + Duration::from_secs(5);
+		while (captured.lock().len() < count && Instant::now() < timeout)
+		{
+			sleep(Duration::from_millis(10));
+		}
+		return captured.lock().clone();
+	}
+
+	fn push(&self, trace: &OneTrace)
+	{
+		self.captured.lock().push((self.describe)(trace));
+	}
+}
+
+impl ModuleAbstract for Capture
+{
+	fn name_set(&mut self, _: String) -> anyhow::Result<()> { Ok(()) }
+	fn name_get(&self) -> anyhow::Result<String> { Ok("capture".to_string()) }
+	fn event_onDebug(&self, trace: &OneTrace) { self.push(trace); }
+	fn event_onDebugErr(&self, trace: &OneTrace) { self.push(trace); }
+	fn event_onNormal(&self, trace: &OneTrace) { self.push(trace); }
+	fn event_onNotice(&self, trace: &OneTrace) { self.push(trace); }
+	fn event_onNoticeErr(&self, trace: &OneTrace) { self.push(trace); }
+	fn event_onWarning(&self, trace: &OneTrace) { self.push(trace); }
+	fn event_onError(&self, trace: &OneTrace) { self.push(trace); }
+	fn event_onFatal(&self, trace: &OneTrace) { self.push(trace); }
+	fn event_onContextExit(&self, _: &Context) {}
+	fn event_onGlobalExit(&self) {}
+}
+
@@ -0,0 +1,123 @@
+#![allow(unused_parens)]
+
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use parking_lot::Mutex;
+use Htrace::components::context::Context;
+use Htrace::components::error::{ResultExt, TraceableError};
+use Htrace::components::hbacktrace::Hbacktrace;
+use Htrace::components::level::Level;
+use Htrace::components::trace::OneTrace;
+#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+use Htrace::crates::bridge::HtraceBridge;
+use Htrace::htracer::HTracer;
+use Htrace::modules::network::Network;
+use Htrace::{HTraceError, Spaned};
+
+#[path = "common/mod.rs"]
+mod common;
+use common::Capture;
+
+#[derive(Debug)]
+struct ConfigError(std::io::Error);
+
+impl Display for ConfigError
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "cannot load config")
+	}
+}
+
+impl std::error::Error for ConfigError
+{
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		Some(&self.0)
+	}
+}
+
+fn config_load() -> Result<String, ConfigError>
+{
+	Err(ConfigError(std::io::Error::new(std::io::ErrorKind::NotFound, "no such file")))
+}
+
+#[test]
+fn error_causes()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.module_add("capture", Capture::with(captured.clone(), |trace| {
+		format!("{} | {} | {:?} | {}", trace.level, trace.message, trace.causes, trace.backtraces.is_empty())
+	}));
+	{
+		Spaned!(context);
+		HTraceError!((Level::WARNING) config_load());
+		HTraceError!("while starting : {}", config_load());
+		let boxed: Result<(), Box<dyn std::error::Error>> = Err(Box::new(config_load().unwrap_err()));
+		HTraceError!((Level::NOTICE) boxed);
+		HTraceError!((Level::NOTICE) Err::<(), String>("plain string".to_string()));
+		let anyhow_error: Result<(), anyhow::Error> = Err(anyhow::Error::new(config_load().unwrap_err()).context("starting server"));
+		HTraceError!((Level::NOTICE) anyhow_error);
+	}
+	let anyhow_backtrace = anyhow::anyhow!("check").backtrace().status() == BacktraceStatus::Captured;
+
+	assert_eq!(Capture::wait(&captured, 5), vec![
+		r#"WARN | cannot load config : no such file | ["no such file"] | true"#.to_string(),
+		r#"ERR  | while starting : cannot load config : no such file | ["no such file"] | false"#.to_string(),
+		r#"NOTI | cannot load config : no such file | ["no such file"] | true"#.to_string(),
+		r#"NOTI | plain string | [] | true"#.to_string(),
+		// anyhow backtrace (captured depending on RUST_BACKTRACE / RUST_LIB_BACKTRACE) is used for any level
+		format!(r#"NOTI | starting server : cannot load config : no such file | ["cannot load config", "no such file"] | {}"#, !anyhow_backtrace),
+	]);
+}
+
+#[test]
+fn error_result_ext()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.module_add("capture", Capture::with(captured.clone(), |trace| {
+		format!("{}:{} {}", trace.filename.rsplit('/').next().unwrap_or_default(), trace.fileline, trace.message)
+	}));
+	let line;
+	{
+		Spaned!(context);
+		// the result is given back, and traced at the call site
+		line = line!(); let result = config_load().htrace_err(Level::WARNING);
+		assert!(result.is_err());
+		assert_eq!(config_load().htrace_ok_or(Level::WARNING, "default".to_string()), "default");
+		assert_eq!(Ok::<_, ConfigError>(42).htrace_ok_or(Level::WARNING, 0), 42);
+	}
+
+	assert_eq!(Capture::wait(&captured, 2), vec![
+		format!("error.rs:{} cannot load config : no such file", line),
+		format!("error.rs:{} cannot load config : no such file", line + 2),
+	]);
+	assert_eq!(Err::<(), _>(anyhow::anyhow!("root")).unwrap_err().context("top").error_chain(), "top : root");
+}
+
+#[test]
+fn error_encoding()
+{
+	let trace = OneTrace {
+		message: "cannot load config : no such file".to_string(),
+		causes: vec!["no such file".to_string()],
+		..Default::default()
+	};
+	assert!(Network::encodeJson(&trace).contains(r#""causes":["no such file"]"#));
+
+	// std backtraces (like anyhow ones) are converted when captured
+	let frames = Hbacktrace::fromStd(&Backtrace::force_capture());
+	assert!(frames.iter().any(|frame| frame.funcName.contains("error_encoding") && frame.line.is_some()), "{:?}", frames.iter().map(|x| x.to_string()).collect::<Vec<_>>());
+	assert!(Hbacktrace::fromStd(&Backtrace::disabled()).is_empty());
+}
@@ -1,14 +1,26 @@
 #![allow(unused_parens)]
 
 use std::fs;
-use time::macros::datetime;
+use std::sync::Arc;
+use std::time::Duration;
+use parking_lot::Mutex;
+use time::macros::{datetime, offset};
 use Htrace::components::context::Context;
-use Htrace::components::formater::{FormaterCompile, FormaterParamBuilder};
+use Htrace::components::hbacktrace::Hbacktrace;
+use owo_colors::{OwoColorize, Style};
+use Htrace::components::formater::{FormaterCompile, FormaterMultiline, FormaterOptions, FormaterParamBuilder, Formatter, TemplateFormatter};
 use Htrace::components::level::Level;
 use Htrace::components::trace::OneTrace;
 #[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
 use Htrace::crates::bridge::HtraceBridge;
 use Htrace::htracer::HTracer;
+use Htrace::modules::file::File;
+use Htrace::modules::file_config::FileConfig;
+use Htrace::{HTrace, Spaned};
+
+#[path = "common/mod.rs"]
+mod common;
+use common::Capture;
 
 #[test]
 fn formater()
@@ -20,8 +32,8 @@ fn formater()
 	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
 
 	let compiled = FormaterCompile(
-		&"{time} {lvl} ({thread:>, }{file}:l{line} |{extra}{extra[test]}|) : {msg}".to_string(),
-	);
+		&"{time} {lvl} ({thread:>, }{file}:l{line} |{{extra}}{extra[test]}|) : {msg}".to_string(),
+	).unwrap();
 
 	let mut context = Context::default();
 	context.extra_set("test", "cake");
@@ -31,10 +43,11 @@ fn formater()
 			message: "message line".to_string(),
 			date: datetime!(1900-01-01 0:00 UTC),
 			level: Level::DEBUG,
-			context,
+			context: Arc::new(context),
 			filename: "file.rs".to_string(),
 			fileline: 42,
 			backtraces: vec![],
+			..Default::default()
 		},
 		&" | ".to_string(),
 	);
@@ -46,4 +59,255 @@ fn formater()
 
 	// we need to wait all threads are done
 	HTracer::drop();
-}
\ No newline at end of file
+}
+#[test]
+fn formater_template()
+{
+	let mut context = Context::default();
+	context.name_set("request");
+	context.extra_set("user", "  bob  ");
+
+	let parameters = || FormaterParamBuilder(
+		&OneTrace {
+			message: "a long message line".to_string(),
+			date: datetime!(1900-01-01 0:00 UTC),
+			level: Level::WARNING,
+			context: Arc::new(context.clone()),
+			filename: "src/dir/file.rs".to_string(),
+			fileline: 42,
+			backtraces: vec![],
+			..Default::default()
+		},
+		&" | ".to_string(),
+	);
+	let render = |formater: &str| FormaterCompile(&formater.to_string()).unwrap().render(parameters());
+
+	// width, alignment, truncation
+	assert_eq!(render("[{line:<5}][{line:>5}][{line:^6}][{msg:.6}][{file:>4.3}]"), "[42   ][   42][  42  ][a long][ src]");
+	// filters and default values
+	assert_eq!(render("{lvl|lower} {file|basename|upper} [{extra[user]|trim}] {extra[missing]|default(none)}"), "warn FILE.RS [bob] none");
+	// conditional sections and legacy prefix/suffix
+	assert_eq!(render("{?context}in {context:<@}{/}{!context}no context{/}{?extra[missing]}hidden{/}"), "in @request");
+	// literal braces
+	assert_eq!(render("{{msg}} = {msg}"), "{msg} = a long message line");
+
+	// compile errors
+	for invalid in ["{unknown}", "{msg", "msg}", "{?msg}unclosed", "{/}", "{msg|nope}", "{msg:x5}", "{extra}", "{extra[key}"]
+	{
+		assert!(FormaterCompile(&invalid.to_string()).is_err(), "'{}' should not compile", invalid);
+	}
+}
+
+#[test]
+fn formater_time()
+{
+	let parameters = FormaterParamBuilder(
+		&OneTrace {
+			message: "message line".to_string(),
+			date: datetime!(2024-01-31 13:45:10.5 +2),
+			level: Level::NORMAL,
+			context: Arc::new(Context::default()),
+			filename: "file.rs".to_string(),
+			fileline: 42,
+			backtraces: vec![],
+			..Default::default()
+		},
+		&" | ".to_string(),
+	);
+	let render = |formater: &str| FormaterCompile(&formater.to_string()).unwrap().render(parameters.clone());
+
+	assert_eq!(render("{time}"), "13:45:10.500000");
+	assert_eq!(render("{time:rfc3339}"), "2024-01-31T13:45:10.5+02:00");
+	assert_eq!(render("{time:iso_date}"), "2024-01-31");
+	assert_eq!(render("{time:unix} {time:unix_ms}"), "1706701510 1706701510500");
+	assert_eq!(render("{time:[day]/[month]/[year] [hour]h[minute]}"), "31/01/2024 13h45");
+	assert_eq!(render("{time:<[}"), "[13:45:10.500000");
+	assert!(FormaterCompile(&"{time:[nope]}".to_string()).is_err());
+	assert!(FormaterCompile(&"{msg:rfc3339}".to_string()).is_err());
+
+	// the offset of the context is applied to the trace date
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.timeOffset_set(Some(offset!(+5:30)));
+	context.module_add("capture", Capture::with(captured.clone(), |trace| trace.date.offset().to_string()));
+	{
+		Spaned!(context);
+		Spaned!("child span");
+		HTrace!("with offset");
+	}
+	assert_eq!(Capture::wait(&captured, 1), vec!["+05:30:00"]);
+}
+
+/// stateful formatter, numbering its traces
+struct Numbered
+{
+	count: Mutex<u32>,
+}
+
+impl Formatter for Numbered
+{
+	fn format(&self, trace: &OneTrace, _: Option<&Style>) -> String
+	{
+		let mut count = self.count.lock();
+		*count += 1;
+		return format!("{} {}", count, trace.message);
+	}
+}
+
+#[test]
+fn formater_trait()
+{
+	let trace = OneTrace {
+		message: "message line".to_string(),
+		level: Level::WARNING,
+		..Default::default()
+	};
+
+	// highlight is applied after the padding
+	let template = TemplateFormatter::new("[{lvl:<6}] {msg:>13}", " | ").unwrap();
+	let style = Style::new().red();
+	assert_eq!(template.format(&trace, None), "[WARN  ]  message line");
+	assert_eq!(template.format(&trace, Some(&style)), format!("[{}  ]  {}", "WARN".style(style), "message line".style(style)));
+	assert!(TemplateFormatter::new("{nope}", " | ").is_err());
+
+	// closures
+	let closure: Box<dyn Formatter> = Box::new(|trace: &OneTrace| trace.message.to_uppercase());
+	assert_eq!(closure.format(&trace, Some(&style)), "MESSAGE LINE");
+
+	// stateful formatter used by a module
+	let path = std::env::temp_dir().join(format!("htrace_formatter_{}", std::process::id()));
+	let _ = fs::remove_dir_all(&path);
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let mut config = FileConfig::default();
+	config.path = path.to_string_lossy().to_string();
+	config.byThreadId = false;
+	config.forceInOneFile = Some("numbered".to_string());
+	config.formatter = Some(Box::new(Numbered { count: Mutex::new(0) }));
+	let mut context = Context::default();
+	context.module_add("file", File::new(config));
+	{
+		Spaned!(context);
+		HTrace!("first");
+		HTrace!("second");
+	}
+	HTracer::drop();
+
+	let file = fs::read_dir(&path).unwrap().next().unwrap().unwrap().path();
+	assert_eq!(fs::read_to_string(file).unwrap(), "1 first\n2 second\n");
+	let _ = fs::remove_dir_all(&path);
+}
+
+#[test]
+fn formater_render_into()
+{
+	let mut context = Context::default();
+	context.name_set("request");
+	context.extra_set("user", "bob");
+
+	let traces = [
+		OneTrace {
+			message: "simple".to_string(),
+			date: datetime!(2024-01-31 13:45:10.123456789 UTC),
+			level: Level::NOTICE,
+			context: Arc::new(context.clone()),
+			filename: "src/file.rs".to_string(),
+			fileline: 42,
+			fileColumn: 5,
+			modulePath: "mycrate::file",
+			funcName: "Client::connect",
+			sequence: 3,
+			uptime: Duration::from_millis(1500),
+			delta: Some(Duration::from_micros(20)),
+			spanDuration: Some(Duration::from_millis(250)),
+			..Default::default()
+		},
+		OneTrace {
+			message: "multi\nline\r\nwith \\n escaped\\n\\r and \\ backslash\n\r".to_string(),
+			level: Level::ERROR,
+			backtraces: vec![Hbacktrace {
+				funcName: "main()".to_string(),
+				fileName: Some("src/main.rs".to_string()),
+				line: Some(7),
+			}],
+			..Default::default()
+		},
+		OneTrace::default(),
+	];
+
+	// the direct rendering give the same result as the parameters rendering
+	let formaters = [
+		"{time} {lvl} ({thread:>, }{context:>, }{file}:l{line}) : {msg}",
+		"[{lvl:^6}] {msg:.10} {file|basename|upper:>12} {line:<4}|",
+		"{?context}in {context}{/}{!context}no context{/} {extra[user]|default(nobody)} {extra[missing]:<@}",
+		"#{sequence} {uptime}s {delta:<+}{span_duration:>s }{time:rfc3339} {time:unix_ms} {time:[year]/[month]}",
+		"{module}::{func} {file}:{line}:{column} {?func}in {func|upper}{/}",
+	];
+	let style = Style::new().red();
+	let options = FormaterOptions::default();
+	for formater in formaters
+	{
+		let compiled = FormaterCompile(&formater.to_string()).unwrap();
+		for trace in &traces
+		{
+			let expected = compiled.render_highlight(FormaterParamBuilder(trace, &" | ".to_string()), Some(&style));
+			let mut output = String::new();
+			compiled.render_into(&mut output, trace, &options, Some(&style)).unwrap();
+			assert_eq!(output, expected, "formater '{}'", formater);
+
+			let mut bytes = vec![];
+			compiled.write_into(&mut bytes, trace, &options, Some(&style)).unwrap();
+			assert_eq!(String::from_utf8(bytes).unwrap(), expected, "formater '{}'", formater);
+		}
+	}
+}
+
+#[test]
+fn formater_multiline()
+{
+	let trace = OneTrace {
+		message: "first\nsecond\r\nC:\\new\\dir".to_string(),
+		level: Level::ERROR,
+		backtraces: vec![
+			Hbacktrace {
+				funcName: "main()".to_string(),
+				fileName: Some("src/main.rs".to_string()),
+				line: Some(7),
+			},
+			Hbacktrace {
+				funcName: "start()".to_string(),
+				fileName: None,
+				line: None,
+			},
+		],
+		..Default::default()
+	};
+	let render = |formater: &str, multiline: FormaterMultiline, interpret_escapes: bool| {
+		let formatter = TemplateFormatter::with_options(formater, FormaterOptions {
+			lineReturn: " | ".to_string(),
+			multiline,
+			interpretEscapes: interpret_escapes,
+		}).unwrap();
+		formatter.format(&trace, None)
+	};
+
+	// indent (default), escaped line returns interpreted or not
+	assert_eq!(render("{lvl}: {msg}", FormaterMultiline::Indent, true), "ERR : first\n | second\n | \n | C:\n | ew\\dir, with : \n | src/main.rs(7): main()\n | - start()");
+	assert_eq!(render("{lvl}: {msg}", FormaterMultiline::Indent, false), "ERR : first\n | second\n | \n | C:\\new\\dir, with : \n | src/main.rs(7): main()\n | - start()");
+	// separated backtraces
+	assert_eq!(render("{msg}{backtrace:<\n  at:}", FormaterMultiline::Indent, false), "first\n | second\n | \n | C:\\new\\dir\n  at:\n | src/main.rs(7): main()\n | - start()");
+	// escaped, on one line
+	assert_eq!(render("{lvl}: {msg} {backtrace}", FormaterMultiline::Escape, false), "ERR : first\\nsecond\\n\\nC:\\new\\dir \\nsrc/main.rs(7): main()\\n- start()");
+	// one line per message line, then per backtrace
+	assert_eq!(render("{lvl}: {msg}", FormaterMultiline::Split, false), "ERR : first\nERR : second\nERR : \nERR : C:\\new\\dir, with : \nERR : src/main.rs(7): main()\nERR : - start()");
+	assert_eq!(render("[{msg}]{backtrace}", FormaterMultiline::Split, false), "[first]\n[second]\n[]\n[C:\\new\\dir]\n[src/main.rs(7): main()]\n[- start()]");
+	assert_eq!(FormaterMultiline::fromName(FormaterMultiline::Split.name()), Some(FormaterMultiline::Split));
+}
@@ -0,0 +1,148 @@
+#![allow(unused_parens)]
+
+use std::net::UdpSocket;
+use std::sync::Arc;
+use std::time::Duration;
+use time::macros::datetime;
+use Htrace::components::context::Context;
+use Htrace::components::hbacktrace::Hbacktrace;
+use Htrace::components::level::Level;
+use Htrace::components::trace::OneTrace;
+use Htrace::modules::gelf::Gelf;
+use Htrace::modules::gelf_config::GelfConfig;
+use Htrace::modules::module_abstract::ModuleAbstract;
+
+fn trace(message: &str, level: Level) -> OneTrace
+{
+	let mut context = Context::default();
+	context.name_set("request");
+	context.extra_set("request_id", "42");
+	context.extra_set("id", "forbidden");
+
+	return OneTrace {
+		message: message.to_string(),
+		date: datetime!(2000-01-01 0:00 UTC),
+		level,
+		context: Arc::new(context),
+		filename: "file.rs".to_string(),
+		fileline: 42,
+		backtraces: vec![Hbacktrace {
+			funcName: "gelf::trace()".to_string(),
+			fileName: Some("file.rs".to_string()),
+			line: Some(42),
+		}],
+		..Default::default()
+	};
+}
+
+#[test]
+fn gelf_udp()
+{
+	let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+	receiver.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+	let mut config = GelfConfig::default();
+	config.address = receiver.local_addr().unwrap().to_string();
+	config.host = "test".to_string();
+	let module = Gelf::new(config);
+
+	module.event_onError(&trace("gelf message", Level::ERROR));
+
+	let mut buffer = [0u8; 2048];
+	let size = receiver.recv(&mut buffer).unwrap();
+	assert_eq!(
+		String::from_utf8_lossy(&buffer[..size]),
+		r#"{"version":"1.1","host":"test","short_message":"gelf message","full_message":"gelf message\nfile.rs(42): gelf::trace()","timestamp":946684800,"level":3,"_level_name":"ERROR","_file":"file.rs","_line":42,"_context":"request","_extra_id":"forbidden","_request_id":"42"}"#,
+		"gelf encoding changed"
+	);
+}
+
+#[test]
+fn gelf_udp_chunked()
+{
+	let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+	receiver.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+	let mut config = GelfConfig::default();
+	config.address = receiver.local_addr().unwrap().to_string();
+	config.chunkSize = 112;
+	let module = Gelf::new(config);
+
+	let message = "x".repeat(500);
+	module.event_onNormal(&trace(&message, Level::NORMAL));
+
+	let mut chunks = vec![];
+	let mut buffer = [0u8; 2048];
+	loop
+	{
+		let size = receiver.recv(&mut buffer).unwrap();
+		assert!(size <= 112);
+		assert_eq!(&buffer[..2], &[0x1e, 0x0f], "missing gelf chunk magic bytes");
+		chunks.push(buffer[..size].to_vec());
+		if(chunks.len() == buffer[11] as usize)
+		{
+			break;
+		}
+	}
+
+	chunks.sort_by_key(|chunk| chunk[10]);
+	let payload = chunks.iter().flat_map(|chunk| chunk[12..].to_vec()).collect::<Vec<_>>();
+	assert!(String::from_utf8_lossy(&payload).contains(&message));
+}
+
+#[test]
+fn gelf_udp_truncated()
+{
+	let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+	receiver.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+	let mut config = GelfConfig::default();
+	config.address = receiver.local_addr().unwrap().to_string();
+	config.chunkSize = 112;
+	let module = Gelf::new(config);
+
+	// more than 128 chunks of 100 bytes : the messages are truncated
+	module.event_onNormal(&trace(&"x".repeat(20_000), Level::NORMAL));
+
+	let mut chunks = vec![];
+	let mut buffer = [0u8; 2048];
+	loop
+	{
+		let size = receiver.recv(&mut buffer).unwrap();
+		chunks.push(buffer[..size].to_vec());
+		if(chunks.len() == buffer[11] as usize)
+		{
+			break;
+		}
+	}
+
+	chunks.sort_by_key(|chunk| chunk[10]);
+	let payload = String::from_utf8(chunks.iter().flat_map(|chunk| chunk[12..].to_vec()).collect::<Vec<_>>()).unwrap();
+	assert!(chunks.len() <= 128);
+	assert!(payload.starts_with(r#"{"version":"1.1""#) && payload.ends_with('}'));
+	assert!(payload.contains(r#"x (truncated)","full_message":"xxx"#));
+	assert!(payload.contains(r#"x (truncated)","timestamp""#));
+}
+
+#[test]
+fn gelf_reserved_fields()
+{
+	let mut config = GelfConfig::default();
+	config.host = "test".to_string();
+	let module = Gelf::new(config);
+
+	let mut trace = trace("", Level::NORMAL);
+	let mut context = Context::default();
+	context.extra_set("file", "extra.rs");
+	context.extra_set("thread", "worker");
+	context.extra_set("extra_id", "42");
+	context.extra_set("id", "forbidden");
+	trace.context = Arc::new(context);
+	trace.backtraces.clear();
+
+	// extras can't replace a field of the module, and the message is never empty
+	assert_eq!(
+		module.encode(&trace),
+		r#"{"version":"1.1","host":"test","short_message":"(empty message)","timestamp":946684800,"level":6,"_level_name":"NORMAL","_file":"file.rs","_line":42,"_extra_extra_id":"42","_extra_file":"extra.rs","_extra_id":"forbidden","_extra_thread":"worker"}"#
+	);
+}
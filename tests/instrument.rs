@@ -0,0 +1,79 @@
+#![allow(unused_parens)]
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll, Waker};
+use parking_lot::Mutex;
+use Htrace::components::context::Context;
+use Htrace::components::instrument::HtraceFutureExt;
+#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+use Htrace::crates::bridge::HtraceBridge;
+use Htrace::htracer::HTracer;
+use Htrace::{HTrace, Spaned};
+
+#[path = "common/mod.rs"]
+mod common;
+use common::Capture;
+
+/// return Pending once, like a task switch of an async runtime
+struct YieldNow(bool);
+
+impl Future for YieldNow
+{
+	type Output = ();
+
+	fn poll(mut self: Pin<&mut Self>, _: &mut TaskContext<'_>) -> Poll<()> {
+		if(self.0)
+		{
+			return Poll::Ready(());
+		}
+		self.0 = true;
+		return Poll::Pending;
+	}
+}
+
+#[test]
+fn instrument()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let task = |name: &str| {
+		let mut context = Context::default();
+		context.name_set(name);
+		context.module_add("capture", Capture::new(captured.clone()));
+		let name = name.to_string();
+		Box::pin(async move {
+			HTrace!("{} start", name);
+			Spaned!(format!("{} span", name));
+			YieldNow(false).await;
+			HTrace!("{} end", name);
+		}.with_htrace_context(context))
+	};
+
+	// both tasks are interleaved on the same thread, the span of a task must not leak into the other one
+	let mut task_a = task("task A");
+	let mut task_b = task("task B");
+	let mut cx = TaskContext::from_waker(Waker::noop());
+	assert!(task_a.as_mut().poll(&mut cx).is_pending());
+	assert!(task_b.as_mut().poll(&mut cx).is_pending());
+	HTrace!("outside of tasks");
+	assert!(task_a.as_mut().poll(&mut cx).is_ready());
+	assert!(task_b.as_mut().poll(&mut cx).is_ready());
+
+	let mut captured = Capture::wait(&captured, 4);
+	captured.sort();
+	assert_eq!(captured, vec![
+		"task A : task A start",
+		"task A span : task A end",
+		"task B : task B start",
+		"task B span : task B end",
+	]);
+
+	// we need to wait all threads are done
+	HTracer::drop();
+}
@@ -0,0 +1,123 @@
+#![allow(unused_parens)]
+
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, UdpSocket};
+use std::sync::Arc;
+use std::time::Duration;
+use time::macros::datetime;
+use Htrace::components::context::Context;
+use Htrace::components::level::Level;
+use Htrace::components::trace::OneTrace;
+use Htrace::modules::module_abstract::ModuleAbstract;
+use Htrace::modules::network::Network;
+use Htrace::modules::network_config::{NetworkBuffer, NetworkConfig, NetworkEncoding, NetworkProtocol};
+
+fn trace(message: &str) -> OneTrace
+{
+	let mut context = Context::default();
+	context.extra_set("test", "cake");
+
+	return OneTrace {
+		message: message.to_string(),
+		date: datetime!(1900-01-01 0:00 UTC),
+		level: Level::NORMAL,
+		context: Arc::new(context),
+		filename: "file.rs".to_string(),
+		fileline: 42,
+		backtraces: vec![],
+		..Default::default()
+	};
+}
+
+#[test]
+fn network_tcp_reconnect()
+{
+	// reserve a port, then close it to simulate an aggregator that is down
+	let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+	let mut config = NetworkConfig::default();
+	config.address = address.to_string();
+	config.encoding = NetworkEncoding::Json;
+	config.reconnectDelayMin = Duration::ZERO;
+	let module = Network::new(config);
+
+	// buffered while disconnected
+	module.event_onNormal(&trace("first"));
+
+	let listener = TcpListener::bind(address).unwrap();
+	module.event_onNormal(&trace("second"));
+
+	let (stream, _) = listener.accept().unwrap();
+	let mut lines = BufReader::new(stream).lines();
+	let first = lines.next().unwrap().unwrap();
+	let second = lines.next().unwrap().unwrap();
+	assert_eq!(
+		first,
+		r#"{"timestamp":"1900-01-01T00:00:00Z","sequence":0,"level":"NORMAL","levelValue":2,"message":"first","file":"file.rs","line":42,"column":0,"module":"","function":"","thread":"","context":"","extras":{"test":"cake"},"backtraces":[]}"#,
+		"json encoding changed"
+	);
+	assert!(second.contains(r#""message":"second""#));
+}
+
+#[test]
+fn network_disk_buffer()
+{
+	let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+	let path = std::env::temp_dir().join(format!("htrace_network_{}.buffer", address.port()));
+	let _ = std::fs::remove_file(&path);
+
+	let mut config = NetworkConfig::default();
+	config.address = address.to_string();
+	config.encoding = NetworkEncoding::Rendered;
+	config.lineFormat = "{msg}".to_string();
+	config.reconnectDelayMin = Duration::ZERO;
+	config.buffer = NetworkBuffer::Disk(path.to_string_lossy().to_string());
+	config.bufferMaxTraces = 2;
+	let module = Network::new(config);
+
+	// the new traces are dropped above bufferMaxTraces (the file is only appended)
+	module.event_onNormal(&trace("first"));
+	module.event_onNormal(&trace("second"));
+	module.event_onNormal(&trace("third"));
+
+	// a record interrupted while written is not sent
+	let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+	file.write_all(&[0, 0, 0, 50, b'p', b'a', b'r']).unwrap();
+	drop(file);
+
+	let listener = TcpListener::bind(address).unwrap();
+	module.event_onNormal(&trace("fourth"));
+
+	let (stream, _) = listener.accept().unwrap();
+	drop(module);
+	let lines = BufReader::new(stream).lines().map(|line| line.unwrap()).collect::<Vec<_>>();
+	assert_eq!(lines, vec!["first", "second", "fourth"]);
+	assert!(!path.exists());
+}
+
+#[test]
+fn network_udp()
+{
+	let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+	receiver.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+	let mut config = NetworkConfig::default();
+	config.address = receiver.local_addr().unwrap().to_string();
+	config.protocol = NetworkProtocol::Udp;
+	config.encoding = NetworkEncoding::Rendered;
+	config.lineFormat = "{lvl}|{file}:l{line} : {msg}".to_string();
+	let module = Network::new(config);
+
+	module.event_onNormal(&trace("udp message"));
+
+	let mut buffer = [0u8; 1024];
+	let size = receiver.recv(&mut buffer).unwrap();
+	assert_eq!(String::from_utf8_lossy(&buffer[..size]), "    |file.rs:l42 : udp message");
+
+	// a trace too big for a datagram is dropped, without blocking the next ones
+	module.event_onNormal(&trace(&"x".repeat(70_000)));
+	module.event_onNormal(&trace("after"));
+	let size = receiver.recv(&mut buffer).unwrap();
+	assert_eq!(String::from_utf8_lossy(&buffer[..size]), "    |file.rs:l42 : after");
+}
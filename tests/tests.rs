@@ -1,5 +1,10 @@
 mod formater;
 mod trace;
+mod error;
+mod network;
+mod gelf;
+mod instrument;
+mod thread;
 #[cfg(feature = "tracing_consumer")]
 mod tracing;
 #[cfg(feature = "log_consumer")]
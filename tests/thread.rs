@@ -0,0 +1,229 @@
+#![allow(unused_parens)]
+
+use std::fs;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use parking_lot::Mutex;
+use Htrace::components::context::Context;
+#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+use Htrace::crates::bridge::HtraceBridge;
+use Htrace::htracer::HTracer;
+use Htrace::modules::file::File;
+use Htrace::modules::file_config::FileConfig;
+use Htrace::{HTrace, Spaned};
+
+#[path = "common/mod.rs"]
+mod common;
+use common::Capture;
+
+#[test]
+fn thread_context_stacks()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.name_set("main span");
+	context.module_add("capture", Capture::with(captured.clone(), |trace| {
+		format!("{} : {} : {}", trace.context.threadName_get().clone().unwrap_or_default(), trace.context.name_get().clone().unwrap_or_default(), trace.message)
+	}));
+	Spaned!(context);
+
+	// unnamed threads, and threads sharing the same name, have their own stack
+	let barrier = Arc::new(Barrier::new(3));
+	let workers = ["worker", "worker", ""].map(|name| {
+		let barrier = barrier.clone();
+		let captured = captured.clone();
+		let mut builder = thread::Builder::new();
+		if(!name.is_empty())
+		{
+			builder = builder.name(name.to_string());
+		}
+		builder.spawn(move || {
+			let mut context = Context::default();
+			context.module_add("capture", Capture::new(captured));
+			Spaned!(context);
+			barrier.wait();
+			{
+				Spaned!("worker span");
+				barrier.wait();
+			}
+			barrier.wait();
+			HTrace!("after worker span");
+		}).unwrap()
+	});
+	workers.into_iter().for_each(|worker| worker.join().unwrap());
+
+	HTrace!("main thread");
+
+	let mut captured = Capture::wait(&captured, 4);
+	captured.sort();
+	assert_eq!(captured, vec![
+		" : after worker span",
+		" : after worker span",
+		" : after worker span",
+		"MAIN : main span : main thread",
+	]);
+
+	// we need to wait all threads are done
+	HTracer::drop();
+}
+
+#[test]
+fn thread_spawn()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.name_set("parent span");
+	context.module_add("capture", Capture::with(captured.clone(), |trace| {
+		format!("{} : {} : {}", trace.context.threadName_get().clone().unwrap_or_default(), trace.context.name_get().clone().unwrap_or_default(), trace.message)
+	}));
+	Spaned!(context);
+
+	// the spawned thread is named and inherits the parent contexts
+	Htrace::thread::spawn("pool worker", || {
+		assert_eq!(thread::current().name(), Some("pool worker"));
+		HTrace!("inside spawned thread");
+		Htrace::thread::set_thread_name("renamed worker");
+		assert_eq!(Htrace::thread::thread_name(), "renamed worker");
+		HTrace!("after rename");
+	}).join().unwrap();
+
+	let mut captured = Capture::wait(&captured, 2);
+	captured.sort();
+	assert_eq!(captured, vec![
+		"pool worker : parent span : inside spawned thread",
+		"renamed worker : parent span : after rename",
+	]);
+
+	// we need to wait all threads are done
+	HTracer::drop();
+}
+
+#[test]
+fn thread_extras()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	Htrace::thread::spawn("extras", move || {
+		let mut context = Context::default();
+		context.module_add("capture", Capture::with(captured.clone(), |trace| {
+			format!("{:?} {:?} : {}", trace.context.extra_get("request_id"), trace.context.extra_get("user"), trace.message)
+		}));
+		Spaned!(context);
+
+		Htrace::thread::extra_set("request_id", "42");
+		Htrace::thread::extra_set("user", "thread user");
+		assert_eq!(Htrace::thread::extra_get("request_id"), Some("42".to_string()));
+		HTrace!("thread extras");
+		{
+			// span extras override thread extras
+			let mut context = Context::default();
+			context.extra_set("user", "span user");
+			Spaned!(context);
+			HTrace!("span extras");
+		}
+		Htrace::thread::extra_remove("request_id");
+		HTrace!("removed extra");
+
+		let mut captured = Capture::wait(&captured, 3);
+		captured.sort();
+		assert_eq!(captured, vec![
+			r#"None Some("thread user") : removed extra"#,
+			r#"Some("42") Some("span user") : span extras"#,
+			r#"Some("42") Some("thread user") : thread extras"#,
+		]);
+	}).join().unwrap();
+
+	// we need to wait all threads are done
+	HTracer::drop();
+}
+
+#[test]
+fn thread_context_cache()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.name_set("cached");
+	context.module_add("capture", Capture::with(captured.clone(), |trace| {
+		format!("{} : {} : {} : {}",
+			trace.context.threadName_get().clone().unwrap_or_default(),
+			trace.context.name_get().clone().unwrap_or_default(),
+			trace.context.extra_get("id").cloned().unwrap_or_default(),
+			trace.message)
+	}));
+	Spaned!(context);
+
+	// the resolved context is cached, but follows every change of the thread contexts
+	HTrace!("first");
+	Htrace::thread::extra_set("id", "1");
+	HTrace!("extra set");
+	Htrace::thread::set_thread_name("renamed");
+	HTrace!("thread renamed");
+	{
+		Spaned!("inner");
+		HTrace!("inner span");
+	}
+	Htrace::thread::extra_remove("id");
+	HTrace!("extra removed");
+
+	assert_eq!(Capture::wait(&captured, 5), vec![
+		"MAIN : cached :  : first",
+		"MAIN : cached : 1 : extra set",
+		"renamed : cached : 1 : thread renamed",
+		"renamed : inner : 1 : inner span",
+		"renamed : cached :  : extra removed",
+	]);
+}
+
+#[test]
+fn thread_file_names()
+{
+	let path = std::env::temp_dir().join(format!("htrace_thread_files_{}", std::process::id()));
+	let _ = fs::remove_dir_all(&path);
+	let mut config = FileConfig::default();
+	config.path = path.to_string_lossy().to_string();
+	let mut context = Context::default();
+	context.module_add("file", File::new(config));
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(context);
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(context, HtraceBridge::default());
+
+	// unnamed threads are named by their id, chars unsafe in a file name are replaced
+	let unnamed = thread::spawn(|| {
+		HTrace!("unnamed thread");
+		format!("{:?}", thread::current().id()).replace("ThreadId(", "thread").replace(")", "")
+	}).join().unwrap();
+	thread::Builder::new().name("pool/worker 1".to_string()).spawn(|| {
+		HTrace!("named thread");
+	}).unwrap().join().unwrap();
+	HTracer::drop();
+
+	let mut files = fs::read_dir(&path).unwrap().map(|file| {
+		let name = file.unwrap().file_name().to_string_lossy().to_string();
+		name[..name.rfind('_').unwrap()].to_string()
+	}).collect::<Vec<_>>();
+	files.sort();
+	let mut expected = vec!["pool_worker_1".to_string(), unnamed];
+	expected.sort();
+	assert_eq!(files, expected);
+	let _ = fs::remove_dir_all(&path);
+}
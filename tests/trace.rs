@@ -1,17 +1,37 @@
 #![allow(unused_parens)]
 
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use parking_lot::Mutex;
+use Htrace::components::backtrace_policy::{BacktraceDemangle, BacktracePolicy};
 use Htrace::components::context::Context;
+use Htrace::components::formater::{FormaterCompile, FormaterOptions, FormaterParamBuilder};
+use Htrace::components::hbacktrace::HbacktraceUnresolved;
 use Htrace::components::level::Level;
+use Htrace::components::message::{ErrorChain, IntoTraceMessage};
+use Htrace::components::rate_limit::RateLimit;
+use Htrace::components::sampling::{SampleRate, Sampling, SamplingRule};
+use Htrace::components::span::Span;
+use Htrace::components::trace::OneTrace;
 #[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
 use Htrace::crates::bridge::HtraceBridge;
-use Htrace::{HTrace, HTraceError, Spaned};
+use Htrace::{HDebug, HError, HFatal, HNotice, HTrace, HTraceError, HTraceSource, HWarn, Spaned};
 use Htrace::htracer::HTracer;
 use Htrace::modules::{command_line, file};
+use Htrace::modules::network::Network;
+use Htrace::modules::module_abstract::ModuleAbstract;
 use Htrace::modules::command_line_config::CommandLineConfig;
 use Htrace::modules::file_config::FileConfig;
 
+#[path = "common/mod.rs"]
+mod common;
+use common::Capture;
+
 #[test]
 fn trace()
 {
@@ -66,4 +86,579 @@ fn trace()
 
 	// we need to wait all threads are done
 	HTracer::drop();
-}
\ No newline at end of file
+}
+#[test]
+fn trace_sequence()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.module_add("capture", Capture::with(captured.clone(), |trace| {
+		format!("{} {}", trace.sequence, trace.delta.is_some())
+	}));
+	{
+		Spaned!(context);
+		for i in 0..50
+		{
+			HTrace!("trace {}", i);
+		}
+	}
+
+	// traces are emitted in creation order, even with the same date
+	let captured = Capture::wait(&captured, 50);
+	let sequences = captured.iter().map(|one| one.split(' ').next().unwrap().parse::<u64>().unwrap()).collect::<Vec<_>>();
+	assert!(sequences.windows(2).all(|two| two[0] < two[1]), "traces are not ordered : {:?}", sequences);
+	// only the first trace of this thread have no delta
+	assert!(captured[0].ends_with("false"));
+	assert!(captured[1..].iter().all(|one| one.ends_with("true")));
+
+	let parameters = FormaterParamBuilder(&OneTrace {
+		sequence: 7,
+		uptime: Duration::from_millis(1500),
+		delta: Some(Duration::from_micros(2)),
+		..Default::default()
+	}, &" | ".to_string());
+	let compiled = FormaterCompile(&"#{sequence} {uptime}s{delta:<+}".to_string()).unwrap();
+	assert_eq!(compiled.render(parameters), "#7 1.500000s+0.000002");
+}
+
+struct Client;
+
+impl Client
+{
+	fn connect(&self)
+	{
+		let run = || HTrace!("from a closure");
+		run();
+	}
+}
+
+#[test]
+fn trace_source()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.module_add("capture", Capture::with(captured.clone(), |trace| {
+		format!("{} {} {}:{}:{}", trace.modulePath, trace.funcName, trace.filename, trace.fileline, trace.fileColumn)
+	}));
+	{
+		Spaned!(context);
+		HTrace!("from the test");
+		Client.connect();
+	}
+
+	let source = HTraceSource!();
+	assert_eq!((source.module, source.func, source.file), (module_path!(), "trace_source", "tests/trace.rs"));
+	let module = source.module;
+	assert_eq!(Capture::wait(&captured, 2), vec![
+		format!("{} trace_source tests/trace.rs:{}:3", module, source.line - 4),
+		format!("{} Client::connect tests/trace.rs:{}:16", module, source.line - 24),
+	]);
+}
+
+#[test]
+fn trace_level_macros()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.level_setMin(Some(Level::NOTICE));
+	context.module_add("capture", Capture::with(captured.clone(), |trace| {
+		format!("{} {} {}", trace.level, trace.message, trace.backtraces.is_empty())
+	}));
+
+	let evaluated = std::cell::Cell::new(0);
+	let evaluate = |value: &str| {
+		evaluated.set(evaluated.get() + 1);
+		value.to_string()
+	};
+	{
+		Spaned!(context);
+		// the minimum level is inherited by child contexts
+		Spaned!("child span");
+		assert!(!HTracer::enabled(Level::DEBUG));
+		assert!(HTracer::enabled(Level::NOTICE));
+
+		HDebug!("{}", evaluate("debug"));
+		HTrace!("{}", evaluate("normal"));
+		HNotice!("{}", evaluate("notice"));
+		HWarn!(evaluate("warning"));
+		HError!("{}", evaluate("error"));
+		HFatal!("fatal {}", 42);
+	}
+	assert!(HTracer::enabled(Level::DEBUG));
+
+	// disabled traces don't evaluate their arguments
+	assert_eq!(evaluated.get(), 3);
+	assert_eq!(Capture::wait(&captured, 4), vec!["NOTI notice true", "WARN warning true", "ERR  error false", "FATA fatal 42 false"]);
+}
+
+struct OnlyDisplay;
+
+impl Display for OnlyDisplay
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "only display")
+	}
+}
+
+#[derive(Debug)]
+struct ConfigError(std::io::Error);
+
+impl Display for ConfigError
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "cannot load config")
+	}
+}
+
+impl std::error::Error for ConfigError
+{
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		Some(&self.0)
+	}
+}
+
+#[test]
+fn trace_message()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.module_add("capture", Capture::with(captured.clone(), |trace| trace.message.clone()));
+
+	let config_error = || ConfigError(std::io::Error::new(std::io::ErrorKind::NotFound, "no such file"));
+	{
+		Spaned!(context);
+		let text = "borrowed".to_string();
+		HTrace!(OnlyDisplay);
+		HTrace!(&text);
+		HTrace!(Cow::<str>::Borrowed("cow"));
+		HTrace!(vec![1, 2]);
+		HTrace!("args {} {:?}", 42, "debug");
+		HTrace!(config_error());
+		let result: Result<(), ConfigError> = Err(config_error());
+		HTraceError!((Level::WARNING) result);
+	}
+
+	assert_eq!(Capture::wait(&captured, 7), vec![
+		"only display",
+		"borrowed",
+		"cow",
+		"[1, 2]",
+		"args 42 \"debug\"",
+		"cannot load config : no such file",
+		"cannot load config : no such file",
+	]);
+	assert_eq!(ErrorChain(&config_error()).message_get(), "cannot load config : no such file");
+	assert_eq!(format_args!("{}-{}", 1, 2).message_get(), "1-2");
+}
+
+#[test]
+fn trace_backtrace_policy()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.module_add("capture", Capture::with(captured.clone(), |trace| {
+		let frames = trace.backtraces.iter().map(|one| one.funcName.clone()).collect::<Vec<_>>();
+		let has_std = frames.iter().any(|name| name.starts_with("std::") || name.starts_with("core::"));
+		format!("{} {} {} {}", trace.message, frames.len(), frames.first().cloned().unwrap_or_default(), has_std)
+	}));
+	let this = format!("{}::trace_backtrace_policy()", module_path!());
+	Spaned!(context);
+
+	// default policy : ERROR and above, without std frames
+	HWarn!("default warning");
+	HError!("default error");
+	{
+		let mut context = Context::default();
+		context.backtracePolicy_set(Some(BacktracePolicy {
+			minLevel: Some(Level::WARNING),
+			maxFrames: Some(1),
+			..Default::default()
+		}));
+		Spaned!(context);
+		HWarn!("one frame");
+		{
+			// inherited, and overridden by child contexts
+			let mut context = Context::default();
+			context.backtracePolicy_set(Some(BacktracePolicy {
+				minLevel: Some(Level::WARNING),
+				maxFrames: Some(1),
+				demangle: BacktraceDemangle::Function,
+				..Default::default()
+			}));
+			Spaned!(context);
+			HWarn!("function name");
+		}
+		let mut context = Context::default();
+		context.backtracePolicy_set(Some(BacktracePolicy {
+			excludePrefixes: vec![module_path!().to_string()],
+			hideStd: false,
+			..Default::default()
+		}));
+		Spaned!(context);
+		HError!("excluded");
+		let mut context = Context::default();
+		context.backtracePolicy_set(Some(BacktracePolicy {
+			minLevel: None,
+			..Default::default()
+		}));
+		Spaned!(context);
+		HFatal!("disabled");
+	}
+
+	let captured = Capture::wait(&captured, 6);
+	assert_eq!(captured[0], "default warning 0  false");
+	assert!(captured[1].starts_with("default error ") && captured[1].ends_with(&format!(" {} false", this)), "{}", captured[1]);
+	assert_eq!(captured[2], format!("one frame 1 {} false", this));
+	assert_eq!(captured[3], "function name 1 trace_backtrace_policy() false");
+	assert!(captured[4].starts_with("excluded ") && !captured[4].contains(&this) && captured[4].ends_with(" true"), "{}", captured[4]);
+	assert_eq!(captured[5], "disabled 0  false");
+}
+
+#[test]
+#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+#[allow(deprecated)]
+fn trace_backtrace_bridge_deprecated()
+{
+	// the deprecated bridge level defaults to the one of the backtrace policy
+	let bridge = HtraceBridge::default();
+	assert_eq!(bridge.min_level_backtrace, BacktracePolicy::default().minLevel);
+	assert!(!bridge.isBacktrace(&Level::WARNING) && bridge.isBacktrace(&Level::ERROR));
+
+	let bridge = HtraceBridge {
+		min_level_backtrace: Some(Level::WARNING),
+		..Default::default()
+	};
+	assert!(bridge.isBacktrace(&Level::WARNING) && !bridge.isBacktrace(&Level::NOTICE));
+	let bridge = HtraceBridge {
+		min_level_backtrace: None,
+		..Default::default()
+	};
+	assert!(!bridge.isBacktrace(&Level::FATAL));
+}
+
+#[test]
+fn trace_backtrace_deferred()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	// only captured for the levels of the policy
+	assert!(HTracer::backtrace_forLevel(Level::WARNING).is_none());
+	let unresolved = HTracer::backtrace_forLevel(Level::ERROR).expect("ERROR backtraces are captured by default");
+
+	// symbols are resolved on demand (by the writer thread for traces), the second time from the cache
+	let this = format!("{}::trace_backtrace_deferred()", module_path!());
+	let first = unresolved.resolve().iter().map(|frame| frame.to_string()).collect::<Vec<_>>();
+	let second = unresolved.resolve().iter().map(|frame| frame.to_string()).collect::<Vec<_>>();
+	assert!(first.first().is_some_and(|frame| frame.contains(&this)), "{:?}", first);
+	assert_eq!(first, second);
+
+	let mut trace = OneTrace {
+		backtraceUnresolved: Some(HbacktraceUnresolved::capture(BacktracePolicy::default())),
+		..Default::default()
+	};
+	trace.backtrace_resolve();
+	assert!(trace.backtraceUnresolved.is_none());
+	assert!(trace.backtraces.first().is_some_and(|frame| frame.funcName == this), "{:?}", trace.backtraces.iter().map(|x| x.to_string()).collect::<Vec<_>>());
+}
+
+#[inline(never)]
+fn backtrace_recursive(depth: u32, policy: &BacktracePolicy) -> Vec<String>
+{
+	if(depth == 0)
+	{
+		return HbacktraceUnresolved::capture(policy.clone()).resolve().iter().map(|frame| frame.funcName.clone()).collect();
+	}
+	return backtrace_recursive(depth - 1, policy);
+}
+
+#[test]
+fn trace_backtrace_markers()
+{
+	let recursive = format!("{}::backtrace_recursive()", module_path!());
+	let this = format!("{}::trace_backtrace_markers()", module_path!());
+
+	// starts after Htrace frames, recursive frames are deduplicated (one for the capture line, one for the recursive call)
+	let frames = backtrace_recursive(3, &BacktracePolicy::default());
+	assert_eq!(frames[0], recursive);
+	assert_eq!(frames.iter().filter(|name| **name == recursive).count(), 2, "{:?}", frames);
+	assert!(frames.contains(&this), "{:?}", frames);
+	let frames = backtrace_recursive(3, &BacktracePolicy {
+		dedupe: false,
+		..Default::default()
+	});
+	assert_eq!(frames.iter().filter(|name| **name == recursive).count(), 4, "{:?}", frames);
+
+	// custom markers
+	let frames = backtrace_recursive(3, &BacktracePolicy {
+		startMarkers: vec!["Htrace::".to_string(), "backtrace::".to_string(), recursive.trim_end_matches("()").to_string()],
+		stopMarkers: vec![this.trim_end_matches("()").to_string()],
+		..Default::default()
+	});
+	assert_eq!(frames, vec![this]);
+}
+
+#[test]
+fn trace_rate_limit()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.module_add("capture", Capture::with(captured.clone(), |trace| {
+		format!("{}:{} {}", trace.filename.rsplit('/').next().unwrap_or_default(), trace.fileline, trace.message)
+	}));
+	context.rateLimit_set(Some(RateLimit {
+		maxPerWindow: Some(2),
+		window: Duration::from_millis(300),
+	}));
+	Spaned!(context);
+
+	let line = line!(); let failing = |i: u32| HWarn!("failing {}", i);
+	(0..10).for_each(failing);
+	{
+		// disabled by a child context
+		let mut context = Context::default();
+		context.rateLimit_set(Some(RateLimit {
+			maxPerWindow: None,
+			..Default::default()
+		}));
+		Spaned!(context);
+		(0..3).for_each(|i| HWarn!("unlimited {}", i));
+	}
+	// the next window starts with the summary of the previous one
+	std::thread::sleep(Duration::from_millis(400));
+	failing(10);
+
+	let unlimited = line + 10;
+	assert_eq!(Capture::wait(&captured, 7), vec![
+		format!("trace.rs:{} failing 0", line),
+		format!("trace.rs:{} failing 1", line),
+		format!("trace.rs:{} unlimited 0", unlimited),
+		format!("trace.rs:{} unlimited 1", unlimited),
+		format!("trace.rs:{} unlimited 2", unlimited),
+		format!("trace.rs:{} repeated 8 times in 300ms", line),
+		format!("trace.rs:{} failing 10", line),
+	]);
+}
+
+#[test]
+fn trace_sampling()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	/// count how many times the message is formatted
+	struct Counted(Arc<AtomicUsize>);
+	impl Display for Counted
+	{
+		fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+			self.0.fetch_add(1, Ordering::Relaxed);
+			write!(f, "counted")
+		}
+	}
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.module_add("capture", Capture::with(captured.clone(), |trace| {
+		format!("{} {} {:.2}", trace.level, trace.message, trace.sampleRate)
+	}));
+	context.sampling_set(Some(Sampling {
+		rules: vec![
+			SamplingRule::new(SampleRate::EveryNth(3)).level(Level::DEBUG),
+			SamplingRule::new(SampleRate::Probability(0.0)).context("quiet"),
+			SamplingRule::new(SampleRate::Probability(1.0)).level(Level::NOTICE).file("tests/trace.rs"),
+		],
+	}));
+	Spaned!(context);
+
+	(0..9).for_each(|i| HDebug!("debug {}", i));
+	HNotice!("notice");
+	let formatted = Arc::new(AtomicUsize::new(0));
+	{
+		// dropped before being formatted
+		Spaned!("quiet");
+		(0..5).for_each(|_| HWarn!(Counted(formatted.clone())));
+	}
+	HWarn!("warning");
+
+	assert_eq!(Capture::wait(&captured, 5), vec![
+		"DBUG debug 0 0.33".to_string(),
+		"DBUG debug 3 0.33".to_string(),
+		"DBUG debug 6 0.33".to_string(),
+		"NOTI notice 1.00".to_string(),
+		"WARN warning 1.00".to_string(),
+	]);
+	assert_eq!(formatted.load(Ordering::Relaxed), 0);
+
+	let trace = OneTrace {
+		sampleRate: 0.25,
+		..Default::default()
+	};
+	assert!(Network::encodeJson(&trace).contains(r#""sampleRate":0.25"#));
+	assert!(!Network::encodeJson(&OneTrace::default()).contains("sampleRate"));
+}
+
+/// module keeping the span events
+struct SpanEvents(Arc<Mutex<Vec<String>>>);
+
+impl ModuleAbstract for SpanEvents
+{
+	fn name_set(&mut self, _: String) -> anyhow::Result<()> { Ok(()) }
+	fn name_get(&self) -> anyhow::Result<String> { Ok("span_events".to_string()) }
+	fn event_onDebug(&self, _: &OneTrace) {}
+	fn event_onDebugErr(&self, _: &OneTrace) {}
+	fn event_onNormal(&self, _: &OneTrace) {}
+	fn event_onNotice(&self, _: &OneTrace) {}
+	fn event_onNoticeErr(&self, _: &OneTrace) {}
+	fn event_onWarning(&self, _: &OneTrace) {}
+	fn event_onError(&self, _: &OneTrace) {}
+	fn event_onFatal(&self, _: &OneTrace) {}
+	fn event_onSpanEnter(&self, trace: &OneTrace)
+	{
+		self.0.lock().push(format!("enter {}", trace.context.name_get().clone().unwrap_or_default()));
+	}
+	fn event_onSpanExit(&self, trace: &OneTrace)
+	{
+		let duration = trace.spanDuration.unwrap_or_default();
+		self.0.lock().push(format!("exit {} {}", trace.context.name_get().clone().unwrap_or_default(), duration >= Duration::from_millis(20)));
+	}
+	fn event_onContextExit(&self, context: &Context)
+	{
+		self.0.lock().push(format!("context exit {} {}", context.name_get().clone().unwrap_or_default(), context.id_get()));
+	}
+	fn event_onGlobalExit(&self) {}
+}
+
+#[test]
+fn trace_span()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let events = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.name_set("outer");
+	context.module_add("capture", Capture::with(captured.clone(), |trace| {
+		format!("{} {} {}", trace.level, trace.message.split(" after ").next().unwrap_or_default(), trace.spanDuration.is_some())
+	}));
+	context.module_add("events", SpanEvents(events.clone()));
+	context.spanTraces_set(Some(Level::NOTICE));
+	let outer = context.id_get();
+	let inner: Context = "inner".into();
+	let inner_id = inner.id_get();
+	assert_ne!(outer, inner_id);
+	{
+		Spaned!(context);
+		HNotice!("inside");
+		{
+			Spaned!(inner);
+			std::thread::sleep(Duration::from_millis(20));
+		}
+	}
+
+	assert_eq!(Capture::wait(&captured, 5), vec![
+		"NOTI enter outer true",
+		"NOTI inside true",
+		"NOTI enter inner true",
+		"NOTI exit inner true",
+		"NOTI exit outer true",
+	]);
+	// contexts exit once, when their span is dropped (not when the copies made for the traces are dropped)
+	assert_eq!(Capture::wait(&events, 6), vec![
+		"enter outer".to_string(),
+		"enter inner".to_string(),
+		"exit inner true".to_string(),
+		format!("context exit inner {}", inner_id),
+		"exit outer true".to_string(),
+		format!("context exit outer {}", outer),
+	]);
+	std::thread::sleep(Duration::from_millis(50));
+	assert_eq!(events.lock().len(), 6);
+}
+
+#[test]
+fn trace_span_path()
+{
+	#[cfg(all(not(feature = "tracing_consumer"),not(feature = "log_consumer")))]
+	HTracer::globalContext_set(Context::default());
+	#[cfg(any(feature = "tracing_consumer",feature = "log_consumer"))]
+	HTracer::globalContext_set(Context::default(), HtraceBridge::default());
+
+	let captured = Arc::new(Mutex::new(vec![]));
+	let mut context = Context::default();
+	context.name_set("request");
+	context.module_add("capture", Capture::with(captured.clone(), |trace| {
+		let mut output = String::new();
+		FormaterCompile(&"{span_id}|{span_path}|{msg}".to_string()).unwrap().render_into(&mut output, trace, &FormaterOptions::default(), None).unwrap();
+		output
+	}));
+	let json = Arc::new(Mutex::new(vec![]));
+	context.module_add("json", Capture::with(json.clone(), |trace| Network::encodeJson(trace)));
+
+	let request = Span::new(context.clone());
+	HTrace!("in request");
+	let (first, unnamed, second);
+	{
+		let route = Span::new("route".into());
+		first = route.id_get();
+		HTrace!("in route");
+		{
+			let span = Span::new(Context::default());
+			unnamed = span.id_get();
+			HTrace!("in unnamed");
+		}
+	}
+	{
+		// each span has its own id, even with the same context
+		let route = Span::new("route".into());
+		second = route.id_get();
+		HTrace!("in second route");
+	}
+	let id = request.id_get();
+	drop(request);
+	assert!(id < first && first < unnamed && unnamed < second);
+
+	assert_eq!(Capture::wait(&captured, 4), vec![
+		format!("{}|request#{}|in request", id, id),
+		format!("{}|request#{}/route#{}|in route", first, id, first),
+		format!("{}|request#{}/route#{}/#{}|in unnamed", unnamed, id, first, unnamed),
+		format!("{}|request#{}/route#{}|in second route", second, id, second),
+	]);
+	assert!(Capture::wait(&json, 4)[1].contains(&format!(r#""spanId":{},"spanPath":["request#{}","route#{}"]"#, first, id, first)));
+}
@@ -1,13 +1,40 @@
 #![allow(unused_parens)]
 
 use std::fs;
+use std::sync::Arc;
+use parking_lot::Mutex;
 use Htrace::components::context::Context;
 use Htrace::components::level::Level;
+use Htrace::components::trace::OneTrace;
 use Htrace::crates::bridge::HtraceBridge;
 use Htrace::htracer::HTracer;
 use Htrace::modules::{command_line, file};
 use Htrace::modules::command_line_config::CommandLineConfig;
 use Htrace::modules::file_config::FileConfig;
+use Htrace::modules::module_abstract::ModuleAbstract;
+
+#[path = "common/mod.rs"]
+mod common;
+use common::Capture;
+
+/// module keeping the ids of the exited contexts
+struct ContextExits(Arc<Mutex<Vec<u64>>>);
+
+impl ModuleAbstract for ContextExits
+{
+	fn name_set(&mut self, _: String) -> anyhow::Result<()> { Ok(()) }
+	fn name_get(&self) -> anyhow::Result<String> { Ok("exits".to_string()) }
+	fn event_onDebug(&self, _: &OneTrace) {}
+	fn event_onDebugErr(&self, _: &OneTrace) {}
+	fn event_onNormal(&self, _: &OneTrace) {}
+	fn event_onNotice(&self, _: &OneTrace) {}
+	fn event_onNoticeErr(&self, _: &OneTrace) {}
+	fn event_onWarning(&self, _: &OneTrace) {}
+	fn event_onError(&self, _: &OneTrace) {}
+	fn event_onFatal(&self, _: &OneTrace) {}
+	fn event_onContextExit(&self, context: &Context) { self.0.lock().push(context.id_get()); }
+	fn event_onGlobalExit(&self) {}
+}
 
 #[test]
 fn trace_from_tracing_crate()
@@ -21,6 +48,20 @@ fn trace_from_tracing_crate()
 	);
 	global_context.module_add("file", file::File::new(FileConfig::default()));
 	global_context.level_setMin(Some(Level::DEBUG));
+	global_context.spanTraces_set(Some(Level::DEBUG));
+	let captured = Arc::new(Mutex::new(vec![]));
+	global_context.module_add("capture", Capture::with(captured.clone(), |trace| {
+		let path = trace.context.spanPath_get();
+		let names = path.iter().map(|span| span.name.as_str()).collect::<Vec<_>>().join("/");
+		let ordered = path.windows(2).all(|spans| spans[0].id < spans[1].id);
+		format!("{} | {} {} | {:?}", trace.message, names, ordered, trace.context.spanId_get() == path.last().map(|span| span.id))
+	}));
+
+	let ids = Arc::new(Mutex::new(vec![]));
+	global_context.module_add("ids", Capture::with(ids.clone(), |trace| format!("{} {}", trace.message, trace.context.id_get())));
+
+	let exits = Arc::new(Mutex::new(vec![]));
+	global_context.module_add("exits", ContextExits(exits.clone()));
 
 	HTracer::globalContext_set(global_context, HtraceBridge::default());
 
@@ -46,7 +87,39 @@ fn trace_from_tracing_crate()
 
 		tracing::warn!("span withing tracing crate: handler a bit slow");        // visible (dans 'request')
 	});
+	drop(request);
+
+	// entered again, like an instrumented future on each poll
+	let poll = tracing::info_span!("poll");
+	for _ in 0..3
+	{
+		poll.in_scope(|| tracing::info!("polled"));
+	}
+	drop(poll);
+
+	// tracing spans are Htrace spans (with their own ids), giving the span path of the traces
+	let captured = Capture::wait(&captured, 17);
+	let spans = captured.iter().filter(|one| one.starts_with("span withing")).collect::<Vec<_>>();
+	assert_eq!(spans, vec![
+		"span withing tracing crate: start handling | request true | true",
+		"span withing tracing crate: db query done {count=3} | request/route true | true",
+		"span withing tracing crate: handler a bit slow | request true | true",
+	]);
+
+	// a tracing span emit its span events once (entered the first time, exited when closed), and keep its context id
+	let events = captured.iter()
+		.filter(|one| one.starts_with("enter ") || one.starts_with("exit "))
+		.map(|one| one.split(' ').take(2).collect::<Vec<_>>().join(" "))
+		.collect::<Vec<_>>();
+	assert_eq!(events, vec!["enter request", "enter route", "exit route", "exit request", "enter poll", "exit poll"]);
+	let polled = Capture::wait(&ids, 17).into_iter().filter(|one| one.starts_with("polled ")).collect::<Vec<_>>();
+	assert_eq!(polled.len(), 3);
+	assert!(polled.iter().all(|one| one == &polled[0]), "{:?}", polled);
 
 	// we need to wait all threads are done
 	HTracer::drop();
+
+	// the context of a tracing span is exited once, when closed
+	let poll_id = polled[0].rsplit(' ').next().unwrap().parse::<u64>().unwrap();
+	assert_eq!(exits.lock().iter().filter(|id| **id == poll_id).count(), 1);
 }
\ No newline at end of file